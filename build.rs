@@ -4,6 +4,7 @@ use std::{
     process::{Command, Output, Stdio},
 };
 
+#[cfg(feature = "native")]
 use bindgen::Builder;
 
 fn project_root() -> PathBuf {
@@ -33,11 +34,14 @@ dist
             └── librunecoral.a
 
 When this directory is passed as an environment variable, we do not try to build the librunecoral and instead
-use the precompiled libraries from this path.
+use the precompiled libraries from this path. A flat `lib/` directory containing librunecoral is also accepted.
+
+A relative path is resolved against the build script's working directory (which Cargo sets to the crate's
+root), the same way it would be if it was used as-is, and made absolute so it can be passed to the linker.
 */
 fn dist_dir() -> PathBuf {
     match std::env::var("RUNECORAL_DIST_DIR") {
-        Ok(dir) => Path::new(&dir).to_path_buf(),
+        Ok(dir) => std::env::current_dir().unwrap().join(dir),
         _ => project_root()
             .join(std::env::var("OUT_DIR").unwrap())
             .join("dist"),
@@ -72,12 +76,52 @@ fn runecoral_h_path() -> PathBuf {
     dist_dir().join("include")
 }
 
-fn execute_cmd(mut cmd: Command) {
+/// The models and operator names to build librunecoral with when the
+/// `selected_ops` feature is enabled, as a comma-separated list for
+/// `make SELECTED_OPS=...`.
+///
+/// Relative model paths are resolved against the crate's root directory.
+fn selected_ops() -> Option<String> {
+    if !cfg!(feature = "selected_ops") {
+        return None;
+    }
+
+    println!("cargo:rerun-if-env-changed=RUNECORAL_SELECTED_OPS");
+
+    let selected = std::env::var("RUNECORAL_SELECTED_OPS").expect(
+        "The selected_ops feature requires RUNECORAL_SELECTED_OPS to be a comma-separated list of .tflite models and builtin operator names (e.g. \"model.tflite,CONV_2D\")",
+    );
+
+    let entries: Vec<String> = selected
+        .split(',')
+        .map(|entry| entry.trim())
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            if !entry.ends_with(".tflite") {
+                return entry.to_string();
+            }
+
+            let model = project_root().join(entry);
+            println!("cargo:rerun-if-changed={}", model.display());
+            model.display().to_string()
+        })
+        .collect();
+
+    if entries.is_empty() {
+        panic!("RUNECORAL_SELECTED_OPS doesn't list any models or operators");
+    }
+
+    Some(entries.join(","))
+}
+
+fn execute_cmd(mut cmd: Command) -> Result<(), String> {
     let Output {
         stdout,
         stderr,
         status,
-    } = cmd.output().unwrap();
+    } = cmd
+        .output()
+        .map_err(|e| format!("unable to run {:?}: {}", cmd, e))?;
 
     cmd.stdin(Stdio::null())
         .stdout(Stdio::piped())
@@ -94,8 +138,10 @@ fn execute_cmd(mut cmd: Command) {
             println!("Stderr:");
             println!("{}", stderr);
         }
-        panic!("{:?} failed", cmd);
+        return Err(format!("{:?} failed", cmd));
     }
+
+    Ok(())
 }
 
 fn make_runecoral_h() {
@@ -107,7 +153,7 @@ fn make_runecoral_h() {
     .ok();
 }
 
-fn make_librunecoral(target_os: &str) {
+fn make_librunecoral(target_os: &str) -> Result<(), String> {
     // Run the same build command from the README.
     let mut cmd = Command::new("make");
 
@@ -124,11 +170,18 @@ fn make_librunecoral(target_os: &str) {
     if cfg!(feature = "gpu_acceleration") {
         cmd.arg("GPU_ACCELERATION=true");
     }
+    if let Some(selected) = selected_ops() {
+        cmd.arg(format!("SELECTED_OPS={}", selected));
+    }
 
     execute_cmd(cmd)
 }
 
-fn make_librunecoral_windows() {
+fn make_librunecoral_windows() -> Result<(), String> {
+    if selected_ops().is_some() {
+        return Err(String::from("the selected_ops feature isn't supported on Windows yet"));
+    }
+
     // We are doing the job of make
     fs::create_dir_all(librunecoral_path()).unwrap();
 
@@ -155,7 +208,7 @@ fn make_librunecoral_windows() {
 
     cmd.current_dir(project_root());
 
-    execute_cmd(cmd);
+    execute_cmd(cmd)?;
 
     if compilation_mode() == "dbg" {
         println!("cargo:rustc-link-lib=MSVCRTD");
@@ -169,30 +222,221 @@ fn make_librunecoral_windows() {
         librunecoral_path().join("runecoral.lib"),
     )
     .ok();
+
+    Ok(())
 }
 
-fn main() {
-    let header_file = runecoral_h_path().join("runecoral.h");
+/// Find `librunecoral`, tell cargo how to link against it, and return the
+/// directory containing `runecoral.h`.
+///
+/// The discovery strategies are tried in order:
+///
+/// 1. A prebuilt distribution in `$RUNECORAL_DIST_DIR`
+/// 2. A system-wide installation registered with pkg-config (e.g. from
+///    `make install`)
+/// 3. Building librunecoral from source with CMake (`cmake_build` feature,
+///    host Linux builds only)
+/// 4. Building librunecoral from source with `make` and Bazel
+///
+/// When `$RUNECORAL_DIST_DIR` is set it is the only strategy we try, so a typo
+/// doesn't silently fall back to a (very long) build from source.
+fn find_librunecoral(target_os: &str) -> Result<PathBuf, Vec<(&'static str, String)>> {
+    if std::env::var_os("RUNECORAL_DIST_DIR").is_some() {
+        return use_dist_dir().map_err(|reason| vec![("RUNECORAL_DIST_DIR", reason)]);
+    }
 
-    let target_os = std::env::var("CARGO_CFG_TARGET_OS").unwrap();
+    let mut attempts = vec![("RUNECORAL_DIST_DIR", String::from("not set"))];
 
-    if std::env::var("RUNECORAL_DIST_DIR").is_err() {
-        match target_os.as_str() {
-            "windows" => make_librunecoral_windows(),
-            "linux" | "android" | "macos" | "ios" => make_librunecoral(&target_os),
-            _ => panic!("Target OS not supported!"),
-        };
-        make_runecoral_h();
+    match probe_pkg_config() {
+        Ok(include_dir) => return Ok(include_dir),
+        Err(reason) => attempts.push(("pkg-config", reason)),
     }
 
-    println!(
-        "cargo:rustc-link-search={}",
-        project_root()
-            .join(librunecoral_path())
-            .display()
-            .to_string()
+    #[cfg(feature = "cmake_build")]
+    match build_with_cmake(target_os) {
+        Ok(include_dir) => return Ok(include_dir),
+        Err(reason) => attempts.push(("building from source with CMake", reason)),
+    }
+
+    match build_from_source(target_os) {
+        Ok(include_dir) => return Ok(include_dir),
+        Err(reason) => attempts.push(("building from source with Bazel", reason)),
+    }
+
+    Err(attempts)
+}
+
+fn discovery_error(attempts: &[(&'static str, String)]) -> String {
+    let mut msg = String::from("Unable to find librunecoral. Tried:\n");
+
+    for (strategy, reason) in attempts {
+        // pkg-config errors span several lines
+        let reason = reason
+            .lines()
+            .map(str::trim_end)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n      ");
+        msg.push_str(&format!("  - {}: {}\n", strategy, reason));
+    }
+
+    msg.push_str(
+        "Point $RUNECORAL_DIST_DIR at a prebuilt librunecoral, install it with \
+         `make install` so pkg-config can find it, or make sure Docker and Bazel \
+         are available to build it from source.",
     );
+
+    msg
+}
+
+fn use_dist_dir() -> Result<PathBuf, String> {
+    let header_file = runecoral_h_path().join("runecoral.h");
+    if !header_file.exists() {
+        return Err(format!("{} doesn't exist", header_file.display()));
+    }
+
+    // Accept both the lib/<os>/<arch> layout from `make dist` and a flat lib/
+    let candidates = [librunecoral_path(), dist_dir().join("lib")];
+    let lib_dir = candidates
+        .iter()
+        .find(|dir| contains_librunecoral(dir))
+        .ok_or_else(|| {
+            format!(
+                "neither {} nor {} contain librunecoral",
+                candidates[0].display(),
+                candidates[1].display()
+            )
+        })?;
+
+    emit_link_flags(lib_dir);
+
+    Ok(runecoral_h_path())
+}
+
+fn contains_librunecoral(dir: &Path) -> bool {
+    [
+        "librunecoral.a",
+        "librunecoral.so",
+        "librunecoral.dylib",
+        "runecoral.lib",
+    ]
+    .iter()
+    .any(|name| dir.join(name).is_file())
+}
+
+fn probe_pkg_config() -> Result<PathBuf, String> {
+    if selected_ops().is_some() {
+        return Err(String::from(
+            "skipped because the selected_ops feature needs a custom build",
+        ));
+    }
+
+    // Note: pkg-config also emits the link flags for us
+    let library = pkg_config::Config::new()
+        .atleast_version(env!("CARGO_PKG_VERSION"))
+        .probe("runecoral")
+        .map_err(|e| e.to_string())?;
+
+    // pkg-config leaves system directories like /usr/include out of the
+    // cflags, so fall back to the package's includedir
+    let includedir = pkg_config::get_variable("runecoral", "includedir")
+        .ok()
+        .map(PathBuf::from);
+
+    library
+        .include_paths
+        .into_iter()
+        .chain(includedir)
+        .find(|dir| dir.join("runecoral.h").is_file())
+        .ok_or_else(|| String::from("found runecoral.pc, but not runecoral.h"))
+}
+
+/// Build librunecoral with `runecoral/CMakeLists.txt` instead of Bazel.
+///
+/// Build failures are fatal, because falling back to Bazel would only bury
+/// the CMake error.
+#[cfg(feature = "cmake_build")]
+fn build_with_cmake(target_os: &str) -> Result<PathBuf, String> {
+    let host = std::env::var("HOST").unwrap();
+    let target = std::env::var("TARGET").unwrap();
+
+    if target_os != "linux" || host != target {
+        return Err(format!(
+            "only supported for native Linux builds, not {} -> {}",
+            host, target
+        ));
+    }
+    if selected_ops().is_some() {
+        return Err(String::from("the selected_ops feature needs the Bazel build"));
+    }
+
+    let on_off = |enabled: bool| if enabled { "ON" } else { "OFF" };
+
+    let mut config = cmake::Config::new(project_root().join("runecoral"));
+    config
+        .define(
+            "RUNECORAL_EDGETPU_ACCELERATION",
+            on_off(cfg!(feature = "edgetpu_acceleration")),
+        )
+        .define(
+            "RUNECORAL_GPU_ACCELERATION",
+            on_off(cfg!(feature = "gpu_acceleration")),
+        );
+
+    // Reuse an existing TensorFlow checkout instead of downloading one
+    println!("cargo:rerun-if-env-changed=TENSORFLOW_SOURCE_DIR");
+    if let Some(tensorflow) = std::env::var_os("TENSORFLOW_SOURCE_DIR") {
+        config.define("TENSORFLOW_SOURCE_DIR", tensorflow);
+    }
+
+    let dest = config.build();
+
+    emit_link_flags(&dest.join("lib"));
+
+    Ok(dest.join("include"))
+}
+
+fn build_from_source(target_os: &str) -> Result<PathBuf, String> {
+    match target_os {
+        "windows" => make_librunecoral_windows()?,
+        "linux" | "android" | "macos" | "ios" => make_librunecoral(target_os)?,
+        other => return Err(format!("the \"{}\" target OS isn't supported", other)),
+    }
+    make_runecoral_h();
+
+    emit_link_flags(&librunecoral_path());
+
+    Ok(runecoral_h_path())
+}
+
+fn emit_link_flags(lib_dir: &Path) {
+    println!("cargo:rustc-link-search={}", lib_dir.display());
     println!("cargo:rustc-link-lib=runecoral");
+}
+
+fn main() {
+    if !cfg!(feature = "native") {
+        // The hand-written types in rust/ffi.rs are all we need, so we don't
+        // run bindgen (which needs libclang)
+        return;
+    }
+
+    if cfg!(feature = "dynamic") {
+        // We still need the FFI types, but there's nothing to build or link
+        let header_file = project_root().join("runecoral").join("runecoral.h");
+        generate_bindings(&header_file);
+        return;
+    }
+
+    println!("cargo:rerun-if-env-changed=RUNECORAL_DIST_DIR");
+
+    let target_os = std::env::var("CARGO_CFG_TARGET_OS").unwrap();
+
+    let header_file = match find_librunecoral(&target_os) {
+        Ok(include_dir) => include_dir.join("runecoral.h"),
+        Err(attempts) => panic!("{}", discovery_error(&attempts)),
+    };
+
     if cfg!(feature = "gpu_acceleration") {
         println!("cargo:rustc-link-lib=EGL");
         println!("cargo:rustc-link-lib=GLESv2");
@@ -210,15 +454,33 @@ fn main() {
         println!("cargo:rustc-flags=-l dylib=c++");
     }
 
-    let bindings = Builder::default()
+    generate_bindings(&header_file);
+}
+
+#[cfg(not(feature = "native"))]
+fn generate_bindings(_header_file: &Path) {
+    unreachable!("Bindings are only generated for the native feature")
+}
+
+#[cfg(feature = "native")]
+fn generate_bindings(header_file: &Path) {
+    let mut builder = Builder::default()
         .header(header_file.display().to_string())
         .derive_debug(true)
         .derive_copy(true)
         .derive_default(true)
         .prepend_enum_name(false)
-        .rustfmt_bindings(true)
-        .generate()
-        .unwrap();
+        .rustfmt_bindings(true);
+
+    if cfg!(feature = "dynamic") {
+        // Generate a struct which loads every function with dlopen(), failing
+        // if any of them are missing
+        builder = builder
+            .dynamic_library_name("RuneCoral")
+            .dynamic_link_require_all(true);
+    }
+
+    let bindings = builder.generate().unwrap();
 
     let out_dir: PathBuf = std::env::var("OUT_DIR").unwrap().into();
     let dest = out_dir.join("bindings.rs");
@@ -1,7 +1,17 @@
 #pragma once
 
+extern "C" {
+    #include "../runecoral.h"
+}
+
+#include <cstring>
+#include <string>
+#include <utility>
+#include <vector>
+
 #include "utils.h"
 #include "tensorflow/lite/interpreter.h"
+#include "tensorflow/lite/delegates/xnnpack/xnnpack_delegate.h"
 
 #ifdef RUNECORAL_EDGETPU_ACCELERATION
 #include "tflite/public/edgetpu_c.h"
@@ -20,13 +30,63 @@ public:
     virtual bool accelerate(tflite::Interpreter *interpreter) = 0;
 };
 
+class XnnpackAccelerationBackend: public AccelerationBackend {
+    struct TfLiteDelegate* mXnnpackDelegate = nullptr;
+public:
+    XnnpackAccelerationBackend() {
+        TfLiteXNNPackDelegateOptions options = TfLiteXNNPackDelegateOptionsDefault();
+        mXnnpackDelegate = TfLiteXNNPackDelegateCreate(&options);
+    }
+
+    ~XnnpackAccelerationBackend() {
+        if (mXnnpackDelegate) {
+            TfLiteXNNPackDelegateDelete(mXnnpackDelegate);
+        }
+    }
+
+    bool isAvailable() const override {
+        return mXnnpackDelegate != nullptr;
+    }
+
+    bool accelerate(tflite::Interpreter *interpreter) override {
+        if (!isAvailable()) {
+            return false;
+        }
+        return interpreter->ModifyGraphWithDelegate(mXnnpackDelegate) == kTfLiteOk;
+    }
+};
+
 #ifdef RUNECORAL_EDGETPU_ACCELERATION
 class EdgetpuAccelerationBackend: public AccelerationBackend {
     size_t mEdgetpuDeviceCount = 0;
     struct edgetpu_device* mEdgetpuDevices = nullptr;
+    // The device we'll delegate to, if any
+    const struct edgetpu_device* mDevice = nullptr;
+    // Our own copy of the delegate's options
+    std::vector<std::pair<std::string, std::string>> mOptions;
 public:
-    EdgetpuAccelerationBackend() {
+    // Use the device matching preferred, or the first one when it is NULL
+    explicit EdgetpuAccelerationBackend(const RuneCoralEdgetpuDevice *preferred = nullptr,
+                                        const RuneCoralEdgetpuOption *options = nullptr,
+                                        size_t numOptions = 0) {
         mEdgetpuDevices = edgetpu_list_devices(&(mEdgetpuDeviceCount));
+
+        for (size_t i = 0; options && i < numOptions; i++) {
+            mOptions.emplace_back(options[i].name, options[i].value);
+        }
+
+        for (size_t i = 0; i < mEdgetpuDeviceCount; i++) {
+            const auto& device = mEdgetpuDevices[i];
+            if (!preferred || (static_cast<int>(device.type) == static_cast<int>(preferred->type)
+                               && strcmp(device.path, preferred->path) == 0)) {
+                mDevice = &device;
+                break;
+            }
+        }
+
+        if (preferred && !mDevice) {
+            LOG_E("Edgetpu device not found: " << preferred->path);
+        }
     }
 
     ~EdgetpuAccelerationBackend() {
@@ -38,8 +98,7 @@ public:
     }
 
     bool isAvailable() const override {
-        // TODO: Filter the devices based on evnironment variable preferences like USB vs. PCI
-        return mEdgetpuDeviceCount > 0;
+        return mDevice != nullptr;
     }
 
     bool accelerate(tflite::Interpreter *interpreter) override {
@@ -47,9 +106,13 @@ public:
             return false;
         }
 
-        LOG_D("Edgetpu devices found. Trying to Update the interpreter to use the delegate.");
-        const auto& device = mEdgetpuDevices[0];
-        TfLiteDelegate* delegate = edgetpu_create_delegate(device.type, device.path, nullptr, 0);
+        LOG_D("Edgetpu device found. Trying to Update the interpreter to use the delegate.");
+        std::vector<edgetpu_option> options;
+        for (const auto& option : mOptions) {
+            options.push_back(edgetpu_option{option.first.c_str(), option.second.c_str()});
+        }
+
+        TfLiteDelegate* delegate = edgetpu_create_delegate(mDevice->type, mDevice->path, options.data(), options.size());
         return interpreter->ModifyGraphWithDelegate(std::unique_ptr<TfLiteDelegate, decltype(&edgetpu_free_delegate)>(delegate, &edgetpu_free_delegate)) == kTfLiteOk;
     }
 };
@@ -0,0 +1,136 @@
+#pragma once
+
+extern "C" {
+    #include "../runecoral.h"
+}
+
+#include <cstring>
+#include <type_traits>
+#include <vector>
+
+#include "utils.h"
+#include "tensorflow/lite/c/common.h"
+
+// A custom operator registered through op_resolver_add_custom()
+struct CustomOp {
+    // Note: This must be the first field. The interpreter only gives each
+    // node a copy of its TfLiteRegistration, so we point custom_name at this
+    // field and use it to find our way back to the CustomOp.
+    char name[128];
+    RuneCoralCustomOp callbacks;
+
+    static const CustomOp *from_registration(const TfLiteRegistration &registration) {
+        return reinterpret_cast<const CustomOp *>(registration.custom_name);
+    }
+};
+
+static_assert(std::is_standard_layout<CustomOp>::value,
+              "CustomOp::name must be at the start of a CustomOp");
+
+// Per-node state for a custom operator
+struct CustomOpNode {
+    const CustomOp *op = nullptr;
+};
+
+inline RuneCoralTensor to_runecoraltensor(TfLiteContext *context, int index) {
+    RuneCoralTensor result{};
+
+    // Optional inputs may be omitted
+    if (index < 0) {
+        result.name = "";
+        return result;
+    }
+
+    const TfLiteTensor &tensor = context->tensors[index];
+    result.name = tensor.name ? tensor.name : "";
+    result.type = static_cast<RuneCoralElementType>(tensor.type);
+    result.data = tensor.data.raw;
+    result.shape = tensor.dims ? tensor.dims->data : nullptr;
+    result.rank = tensor.dims ? tensor.dims->size : 0;
+    result.bytes = tensor.bytes;
+    return result;
+}
+
+inline std::vector<RuneCoralTensor> to_runecoraltensors(TfLiteContext *context, const TfLiteIntArray *indices) {
+    std::vector<RuneCoralTensor> result;
+
+    for (int i = 0; indices && i < indices->size; i++) {
+        result.push_back(to_runecoraltensor(context, indices->data[i]));
+    }
+
+    return result;
+}
+
+// Find the CustomOp a node was created from by looking up its registration
+inline const CustomOp *find_custom_op(TfLiteContext *context, TfLiteNode *node) {
+    TfLiteIntArray *executionPlan = nullptr;
+    if (context->GetExecutionPlan(context, &executionPlan) != kTfLiteOk) {
+        return nullptr;
+    }
+
+    for (int i = 0; i < executionPlan->size; i++) {
+        TfLiteNode *candidate = nullptr;
+        TfLiteRegistration *registration = nullptr;
+
+        if (context->GetNodeAndRegistration(context, executionPlan->data[i], &candidate, &registration) == kTfLiteOk
+            && candidate == node) {
+            return CustomOp::from_registration(*registration);
+        }
+    }
+
+    return nullptr;
+}
+
+inline void *custom_op_init(TfLiteContext *, const char *, size_t) {
+    return new CustomOpNode{};
+}
+
+inline void custom_op_free(TfLiteContext *, void *buffer) {
+    delete static_cast<CustomOpNode *>(buffer);
+}
+
+inline TfLiteStatus custom_op_prepare(TfLiteContext *context, TfLiteNode *node) {
+    auto *state = static_cast<CustomOpNode *>(node->user_data);
+    state->op = find_custom_op(context, node);
+
+    if (!state->op) {
+        LOG_E("Unable to find the custom op for a node");
+        return kTfLiteError;
+    }
+
+    if (!state->op->callbacks.prepare) {
+        return kTfLiteOk;
+    }
+
+    const auto inputs = to_runecoraltensors(context, node->inputs);
+    const auto outputs = to_runecoraltensors(context, node->outputs);
+    const auto result = state->op->callbacks.prepare(state->op->callbacks.user_data,
+                                                     inputs.data(), inputs.size(),
+                                                     outputs.data(), outputs.size());
+
+    return result == RuneCoralInferenceResult__Ok ? kTfLiteOk : kTfLiteError;
+}
+
+inline TfLiteStatus custom_op_invoke(TfLiteContext *context, TfLiteNode *node) {
+    const auto *state = static_cast<CustomOpNode *>(node->user_data);
+    if (!state->op) {
+        return kTfLiteError;
+    }
+
+    const auto inputs = to_runecoraltensors(context, node->inputs);
+    auto outputs = to_runecoraltensors(context, node->outputs);
+    const auto result = state->op->callbacks.invoke(state->op->callbacks.user_data,
+                                                    inputs.data(), inputs.size(),
+                                                    outputs.data(), outputs.size());
+
+    return result == RuneCoralInferenceResult__Ok ? kTfLiteOk : kTfLiteError;
+}
+
+inline TfLiteRegistration custom_op_registration() {
+    TfLiteRegistration registration{};
+    registration.init = custom_op_init;
+    registration.free = custom_op_free;
+    registration.prepare = custom_op_prepare;
+    registration.invoke = custom_op_invoke;
+    return registration;
+}
@@ -0,0 +1,18 @@
+#include "selectedops.h"
+
+// Generated by gen_selected_ops() from the staged models
+namespace runecoral_models {
+void RegisterSelectedOps(::tflite::MutableOpResolver *resolver);
+}
+
+// Generated by selected_ops.sh from the staged operator names
+namespace runecoral_named_ops {
+void RegisterSelectedOps(::tflite::MutableOpResolver *resolver);
+}
+
+void RegisterSelectedOps(tflite::MutableOpResolver *resolver) {
+#ifdef RUNECORAL_SELECTED_MODEL_OPS
+    runecoral_models::RegisterSelectedOps(resolver);
+#endif
+    runecoral_named_ops::RegisterSelectedOps(resolver);
+}
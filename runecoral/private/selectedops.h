@@ -0,0 +1,7 @@
+#pragma once
+
+#include "tensorflow/lite/mutable_op_resolver.h"
+
+// Register the operators that were selected when librunecoral was built (see
+// runecoral/selected_ops.sh)
+void RegisterSelectedOps(tflite::MutableOpResolver *resolver);
@@ -2,38 +2,124 @@ extern "C" {
     #include "runecoral.h"
 }
 
+#include <cctype>
+#include <cstdint>
 #include <cstring>
+#include <list>
 #include <vector>
 #include <algorithm>
 
+#include "tensorflow/lite/core/api/flatbuffer_conversions.h"
 #include "tensorflow/lite/interpreter.h"
-#include "tensorflow/lite/kernels/register.h"
 #include "tensorflow/lite/model_builder.h"
+#include "tensorflow/lite/mutable_op_resolver.h"
+#include "tensorflow/lite/profiling/buffered_profiler.h"
+#include "tensorflow/lite/schema/schema_generated.h"
+#include "tensorflow/lite/schema/schema_utils.h"
+#include "tensorflow/lite/stderr_reporter.h"
+#include "tensorflow/lite/version.h"
 
 #include "private/accelerationbackends.h"
+#include "private/customops.h"
+#ifdef RUNECORAL_SELECTED_OPS
+#include "private/selectedops.h"
+#else
+#include "tensorflow/lite/kernels/register.h"
+#endif
 #include "private/utils.h"
 
 const char* RUNE_CORAL_MIME_TYPE__TFLITE = "application/tflite-model";
 
+struct ModelFormat {
+    const char *mimetype;
+    // Other mimetypes people use for the same format
+    std::vector<const char *> aliases;
+    // The flatbuffer file identifier, used to detect the format when no
+    // mimetype is given
+    const char *identifier;
+};
+
+const std::vector<ModelFormat> &modelFormats() {
+    static const std::vector<ModelFormat> formats = {
+        {
+            RUNE_CORAL_MIME_TYPE__TFLITE,
+            {"application/tflite", "application/x-tflite", "application/vnd.tflite"},
+            tflite::ModelIdentifier(),
+        },
+    };
+    return formats;
+}
+
+bool mimetypesEqual(const char *left, const char *right) {
+    for (; *left && *right; left++, right++) {
+        if (std::tolower(static_cast<unsigned char>(*left)) != std::tolower(static_cast<unsigned char>(*right))) {
+            return false;
+        }
+    }
+    return *left == *right;
+}
+
+// Check the flatbuffer file identifier, which comes after the root table offset
+bool hasIdentifier(const void *model, size_t model_len, const char *identifier) {
+    const size_t offset = sizeof(flatbuffers::uoffset_t);
+    const size_t len = strlen(identifier);
+    return model && model_len >= offset + len
+           && memcmp(static_cast<const char *>(model) + offset, identifier, len) == 0;
+}
+
 RuneCoralTensor to_runecoraltensor(const TfLiteTensor &tfLiteTensor) {
     RuneCoralTensor result;
     result.name = tfLiteTensor.name;
     result.data = nullptr;
     result.type = static_cast<RuneCoralElementType>(tfLiteTensor.type);
-    result.rank = tfLiteTensor.dims->size;
-    result.shape = tfLiteTensor.dims->data;
+    result.rank = tfLiteTensor.dims ? tfLiteTensor.dims->size : 0;
+    result.shape = tfLiteTensor.dims ? tfLiteTensor.dims->data : nullptr;
+    result.bytes = tfLiteTensor.bytes;
     return result;
 }
 
+// The operators librunecoral was built with. This is every builtin operator,
+// unless librunecoral was built with a selected set of operators.
+class DefaultOpResolver : public tflite::MutableOpResolver {
+public:
+    DefaultOpResolver() {
+#ifdef RUNECORAL_SELECTED_OPS
+        RegisterSelectedOps(this);
+#else
+        AddAll(tflite::ops::builtin::BuiltinOpResolver());
+#endif
+    }
+};
+
+struct RuneCoralOpResolver {
+    DefaultOpResolver resolver;
+    // Note: resolver has pointers into these, so they need stable addresses
+    std::list<CustomOp> customOps;
+
+    ~RuneCoralOpResolver() {
+        for (auto &op : customOps) {
+            if (op.callbacks.destroy) {
+                op.callbacks.destroy(op.callbacks.user_data);
+            }
+        }
+    }
+};
+
 struct RuneCoralContext {
     std::vector<char> model_buffer;
     // Note: model has pointers into model_buffer
     std::unique_ptr<tflite::FlatBufferModel> model;
-    tflite::ops::builtin::BuiltinOpResolver resolver;
-    std::unique_ptr<tflite::Interpreter> interpreter;
+    DefaultOpResolver defaultResolver;
+    // Note: the caller guarantees a custom resolver outlives the context
+    const tflite::OpResolver *resolver = &defaultResolver;
+    // Note: the delegate must outlive the interpreter, so the backend needs to
+    // be declared (and therefore destroyed) before it
     std::unique_ptr<AccelerationBackend> accelerationBackend;
+    std::unique_ptr<tflite::Interpreter> interpreter;
     std::vector<RuneCoralTensor> inputs;
     std::vector<RuneCoralTensor> outputs;
+    std::unique_ptr<tflite::profiling::BufferedProfiler> profiler;
+    std::vector<RuneCoralProfileEvent> profileEvents;
 
     // TODO: See if we can avoid this copy by keeping a reference to the
     // original model data
@@ -41,9 +127,28 @@ struct RuneCoralContext {
         : model_buffer(model, model + model_len) {}
 };
 
+struct RuneCoralModel {
+    std::vector<char> model_buffer;
+    // Note: model has pointers into model_buffer
+    std::unique_ptr<tflite::FlatBufferModel> model;
+    // Note: the graph description has pointers into model_buffer and into
+    // these vectors, so they must not be resized once populated
+    std::vector<std::vector<RuneCoralGraphTensor>> tensors;
+    std::vector<std::vector<RuneCoralOperator>> operators;
+    std::vector<RuneCoralSubgraph> subgraphs;
+
+    RuneCoralModel(const char *model, size_t model_len)
+        : model_buffer(model, model + model_len) {}
+};
+
 int availableAccelerationBackends() {
     int result = RuneCoralAccelerationBackend__None;
     //TODO : Add Runtime checks to this too
+    XnnpackAccelerationBackend xnnpackBackend;
+    if (xnnpackBackend.isAvailable()) {
+        result |= RuneCoralAccelerationBackend__Xnnpack;
+    }
+
 #ifdef RUNECORAL_EDGETPU_ACCELERATION
     EdgetpuAccelerationBackend edgeTpuBackend;
     if (edgeTpuBackend.isAvailable()) {
@@ -58,10 +163,72 @@ int availableAccelerationBackends() {
     return result;
 }
 
-bool accelerateInterpreter(const RuneCoralAccelerationBackend backend, RuneCoralContext *context) {
+const char *runecoral_version() {
+    return RUNECORAL_VERSION;
+}
+
+int runecoral_abi_version() {
+    return RUNECORAL_ABI_VERSION;
+}
+
+size_t runecoral_sizeof_tensor() {
+    return sizeof(RuneCoralTensor);
+}
+
+const char *runecoral_tflite_version() {
+    return TFLITE_VERSION_STRING;
+}
+
+const char *runecoral_delegate_version(RuneCoralAccelerationBackend backend) {
+    switch (backend) {
+    case RuneCoralAccelerationBackend__Xnnpack:
+        // XNNPACK is built as part of TensorFlow Lite
+        return TFLITE_VERSION_STRING;
+#ifdef RUNECORAL_EDGETPU_ACCELERATION
+    case RuneCoralAccelerationBackend__Edgetpu:
+        return edgetpu_version();
+#endif
+#ifdef RUNECORAL_GPU_ACCELERATION
+    case RuneCoralAccelerationBackend__Gpu:
+        return TFLITE_VERSION_STRING;
+#endif
+    default:
+        return nullptr;
+    }
+}
+
+size_t runecoral_edgetpu_devices(RuneCoralEdgetpuDevice *devices, size_t capacity) {
+#ifdef RUNECORAL_EDGETPU_ACCELERATION
+    size_t count = 0;
+    struct edgetpu_device *found = edgetpu_list_devices(&count);
+
+    for (size_t i = 0; i < count && i < capacity; i++) {
+        devices[i].type = static_cast<RuneCoralEdgetpuDeviceType>(found[i].type);
+        strncpy(devices[i].path, found[i].path, sizeof(devices[i].path) - 1);
+        devices[i].path[sizeof(devices[i].path) - 1] = '\0';
+    }
+
+    if (found) {
+        edgetpu_free_devices(found);
+    }
+    return count;
+#else
+    return 0;
+#endif
+}
+
+bool accelerateInterpreter(const RuneCoralContextOptions &options, RuneCoralContext *context) {
+    const RuneCoralAccelerationBackend backend = options.backend;
+
+    if (backend & RuneCoralAccelerationBackend__Xnnpack) {
+        context->accelerationBackend.reset(new XnnpackAccelerationBackend());
+    }
+
 #ifdef RUNECORAL_EDGETPU_ACCELERATION
     if (backend & RuneCoralAccelerationBackend__Edgetpu) {
-        context->accelerationBackend.reset(new EdgetpuAccelerationBackend());
+        context->accelerationBackend.reset(new EdgetpuAccelerationBackend(options.edgetpu_device,
+                                                                      options.edgetpu_options,
+                                                                      options.num_edgetpu_options));
     }
 #endif
 
@@ -75,37 +242,196 @@ bool accelerateInterpreter(const RuneCoralAccelerationBackend backend, RuneCoral
            || (context->accelerationBackend && context->accelerationBackend->accelerate(context->interpreter.get()));
 }
 
+// The custom operator edgetpu_compiler replaces a model's operators with
+const char *EDGETPU_CUSTOM_OP = "edgetpu-custom-op";
+
+// Find the operators in a model which can't be resolved
+std::vector<RuneCoralOpCode> find_unresolved_ops(const tflite::Model &model, const tflite::OpResolver &resolver) {
+    std::vector<RuneCoralOpCode> result;
+    if (!model.operator_codes()) {
+        return result;
+    }
+
+    for (const auto *opcode : *model.operator_codes()) {
+        const auto builtinCode = tflite::GetBuiltinCode(opcode);
+
+        RuneCoralOpCode op{};
+        op.builtin_code = builtinCode;
+        op.op_name = tflite::EnumNameBuiltinOperator(builtinCode);
+        op.version = opcode->version();
+
+        const TfLiteRegistration *registration = nullptr;
+        if (builtinCode == tflite::BuiltinOperator_CUSTOM) {
+            op.custom_name = opcode->custom_code() ? opcode->custom_code()->c_str() : "";
+            registration = resolver.FindOp(op.custom_name, op.version);
+        } else {
+            registration = resolver.FindOp(builtinCode, op.version);
+        }
+
+        if (!registration) {
+            result.push_back(op);
+        }
+    }
+
+    return result;
+}
+
+// Check whether the interpreter will be able to run every operator in the
+// model, allowing for custom operators that a delegate will take over
+bool has_unsupported_ops(const tflite::Model &model, const tflite::OpResolver &resolver,
+                         const RuneCoralAccelerationBackend backend) {
+    for (const auto &op : find_unresolved_ops(model, resolver)) {
+        const bool delegated = (backend & RuneCoralAccelerationBackend__Edgetpu)
+            && op.custom_name && strcmp(op.custom_name, EDGETPU_CUSTOM_OP) == 0;
+
+        if (!delegated) {
+            LOG_E("Unsupported operator: " << (op.custom_name ? op.custom_name : op.op_name) << " v" << op.version);
+            return true;
+        }
+    }
+
+    return false;
+}
+
+// Was the model compiled with edgetpu_compiler?
+bool uses_edgetpu(const tflite::Model &model) {
+    if (!model.operator_codes()) {
+        return false;
+    }
+
+    for (const auto *opcode : *model.operator_codes()) {
+        if (tflite::GetBuiltinCode(opcode) == tflite::BuiltinOperator_CUSTOM && opcode->custom_code()
+            && strcmp(opcode->custom_code()->c_str(), EDGETPU_CUSTOM_OP) == 0) {
+            return true;
+        }
+    }
+
+    return false;
+}
+
+RuneCoralOpResolver *create_op_resolver() {
+    return new RuneCoralOpResolver();
+}
+
+int op_resolver_add_custom(RuneCoralOpResolver *resolver, const char *name, int version, RuneCoralCustomOp op) {
+    if (!(resolver && name && op.invoke) || strlen(name) >= sizeof(CustomOp::name)) {
+        LOG_E("Invalid custom op");
+        if (op.destroy) {
+            op.destroy(op.user_data);
+        }
+        return 1;
+    }
+
+    resolver->customOps.emplace_back();
+    auto &customOp = resolver->customOps.back();
+    strncpy(customOp.name, name, sizeof(customOp.name));
+    customOp.callbacks = op;
+
+    const auto registration = custom_op_registration();
+    resolver->resolver.AddCustom(customOp.name, &registration, version);
+    return 0;
+}
+
+void destroy_op_resolver(RuneCoralOpResolver *resolver) {
+    delete resolver;
+}
+
+size_t supported_mimetypes(const char **mimetypes, size_t len) {
+    const auto &formats = modelFormats();
+    for (size_t i = 0; i < len && i < formats.size(); i++) {
+        mimetypes[i] = formats[i].mimetype;
+    }
+    return formats.size();
+}
+
+const char *resolve_mimetype(const char *mimetype, const void *model, size_t model_len) {
+    for (const auto &format : modelFormats()) {
+        if (!mimetype || !*mimetype) {
+            if (hasIdentifier(model, model_len, format.identifier)) {
+                return format.mimetype;
+            }
+            continue;
+        }
+
+        if (mimetypesEqual(mimetype, format.mimetype)) {
+            return format.mimetype;
+        }
+        for (const char *alias : format.aliases) {
+            if (mimetypesEqual(mimetype, alias)) {
+                return format.mimetype;
+            }
+        }
+    }
+
+    return nullptr;
+}
+
 RuneCoralLoadResult create_inference_context(const char *mimetype, const void *model, size_t model_len,
                                              const RuneCoralAccelerationBackend backend,
                                              RuneCoralContext **inferenceContext) {
-    if (strcmp(mimetype, RUNE_CORAL_MIME_TYPE__TFLITE) != 0) {
-        LOG_E("Invalid Tensor Mimetype");
-        return RuneCoralLoadResult__IncorrectMimeType;
+    RuneCoralContextOptions options{};
+    options.backend = backend;
+    return create_inference_context_with_options(mimetype, model, model_len, &options, inferenceContext);
+}
+
+RuneCoralLoadResult create_inference_context_with_options(const char *mimetype, const void *model, size_t model_len,
+                                                          const RuneCoralContextOptions *options,
+                                                          RuneCoralContext **inferenceContext) {
+    if (!resolve_mimetype(mimetype, model, model_len)) {
+        LOG_E("Unsupported model format");
+        return RuneCoralLoadResult__UnsupportedFormat;
     }
 
-    if (!(model && inferenceContext)) {
+    if (!(model && options && inferenceContext)) {
         return RuneCoralLoadResult__InternalError;
     }
 
     RuneCoralLoadResult result = RuneCoralLoadResult__Ok;
+    const RuneCoralAccelerationBackend backend = options->backend;
 
     RuneCoralContext *context = new RuneCoralContext{(const char *)model, model_len};
+    if (options->resolver) {
+        context->resolver = &options->resolver->resolver;
+    }
 
     context->model = tflite::FlatBufferModel::VerifyAndBuildFromBuffer(
         context->model_buffer.data(),
         context->model_buffer.size()
     );
 
+    const bool needsEdgetpu = context->model && uses_edgetpu(*context->model->GetModel());
+    const bool edgetpuRequested = backend & RuneCoralAccelerationBackend__Edgetpu;
+
+    if (edgetpuRequested && context->model && !needsEdgetpu) {
+        LOG_E("Edgetpu acceleration was requested, but the model wasn't compiled with edgetpu_compiler "
+              "so it will run on the CPU");
+    }
+
     // Create the interpreter
-    if (context->model) {
-        tflite::InterpreterBuilder(*(context->model), context->resolver)(&(context->interpreter));
+    if (needsEdgetpu && !edgetpuRequested) {
+        LOG_E("The model was compiled with edgetpu_compiler, but Edgetpu acceleration wasn't requested");
+        result = RuneCoralLoadResult__EdgetpuRequired;
+    } else if (context->model && has_unsupported_ops(*context->model->GetModel(), *context->resolver, backend)) {
+        result = RuneCoralLoadResult__UnsupportedOperator;
+    } else if (context->model) {
+        tflite::InterpreterBuilder(*(context->model), *context->resolver)(&(context->interpreter));
 
         if (context->interpreter) {
-            if (!accelerateInterpreter(backend, context)) {
+            if (options->preserve_all_tensors) {
+                tflite::InterpreterOptions interpreterOptions;
+                interpreterOptions.SetPreserveAllTensors(true);
+                context->interpreter->ApplyOptions(&interpreterOptions);
+            }
+
+            const bool accelerated = accelerateInterpreter(*options, context);
+            if (!accelerated) {
                 LOG_E("Unable to accelerate interpreter");
             }
 
-            if (context->interpreter->AllocateTensors() != kTfLiteOk) {
+            if (!accelerated && needsEdgetpu) {
+                // Nothing else can run the edgetpu-custom-op
+                result = RuneCoralLoadResult__EdgetpuRequired;
+            } else if (context->interpreter->AllocateTensors() != kTfLiteOk) {
                 LOG_E("Interpreter unable to allocate tensors");
                 result = RuneCoralLoadResult__InternalError;
             } else {
@@ -173,10 +499,204 @@ size_t inference_outputs(const RuneCoralContext * const inferenceContext, const
     return inferenceContext->outputs.size();
 }
 
+RuneCoralInferenceResult inference_reset_variable_tensors(RuneCoralContext *context) {
+    if (!(context && context->interpreter)) {
+        return RuneCoralInferenceResult__Error;
+    }
+
+    return static_cast<RuneCoralInferenceResult>(context->interpreter->ResetVariableTensors());
+}
+
+size_t inference_variable_tensors(const RuneCoralContext * const context, RuneCoralTensor *tensors, size_t capacity) {
+    if (!(context && context->interpreter)) {
+        return 0;
+    }
+
+    const auto &variables = context->interpreter->variables();
+    for (size_t i = 0; i < std::min(capacity, variables.size()); i++) {
+        const auto *tensor = context->interpreter->tensor(variables[i]);
+        tensors[i] = to_runecoraltensor(*tensor);
+        tensors[i].data = tensor->data.raw;
+    }
+
+    return variables.size();
+}
+
+size_t inference_tensors(const RuneCoralContext * const context, RuneCoralTensor *tensors, size_t capacity) {
+    if (!(context && context->interpreter)) {
+        return 0;
+    }
+
+    const size_t count = context->interpreter->tensors_size();
+    for (size_t i = 0; i < std::min(capacity, count); i++) {
+        const auto *tensor = context->interpreter->tensor(i);
+        tensors[i] = to_runecoraltensor(*tensor);
+        tensors[i].data = tensor->data.raw;
+        if (!tensors[i].name) {
+            tensors[i].name = "";
+        }
+    }
+
+    return count;
+}
+
+// The number of bytes between the start of the first tensor and the end of the
+// last, for tensors which are all allocated from the same buffer
+struct Extent {
+    uintptr_t start = UINTPTR_MAX;
+    uintptr_t end = 0;
+
+    void add(const TfLiteTensor &tensor) {
+        const uintptr_t data = reinterpret_cast<uintptr_t>(tensor.data.raw);
+        start = std::min(start, data);
+        end = std::max(end, data + tensor.bytes);
+    }
+
+    size_t size() const {
+        return end > start ? end - start : 0;
+    }
+};
+
+void inference_memory_stats(const RuneCoralContext * const context, RuneCoralMemoryStats *stats) {
+    if (!stats) {
+        return;
+    }
+    *stats = RuneCoralMemoryStats{};
+
+    if (!context) {
+        return;
+    }
+    stats->model = context->model_buffer.size();
+
+    if (!context->interpreter) {
+        return;
+    }
+
+    for (size_t i = 0; i < context->interpreter->subgraphs_size(); i++) {
+        const tflite::Subgraph &subgraph = *context->interpreter->subgraph(i);
+        // Note: arena tensors are packed into one buffer (and may share
+        // memory), so the arena's size is the extent of its tensors rather
+        // than their total size
+        Extent arena, persistentArena;
+
+        for (size_t j = 0; j < subgraph.tensors_size(); j++) {
+            const TfLiteTensor &tensor = *subgraph.tensor(j);
+            if (!tensor.data.raw && tensor.buffer_handle == kTfLiteNullBufferHandle) {
+                continue;
+            }
+
+            if (tensor.buffer_handle != kTfLiteNullBufferHandle || tensor.allocation_type == kTfLiteCustom) {
+                stats->delegate += tensor.bytes;
+                continue;
+            }
+
+            switch (tensor.allocation_type) {
+            case kTfLiteArenaRw:
+                arena.add(tensor);
+                break;
+            case kTfLiteArenaRwPersistent:
+                persistentArena.add(tensor);
+                break;
+            case kTfLiteDynamic:
+                stats->dynamic += tensor.bytes;
+                break;
+            default:
+                // Constant tensors point into the model buffer
+                break;
+            }
+        }
+
+        stats->arena += arena.size();
+        stats->persistent_arena += persistentArena.size();
+    }
+}
+
 void destroy_inference_context(RuneCoralContext *context) {
     delete context;
 }
 
+const char *op_name(const TfLiteRegistration &registration) {
+    if (registration.builtin_code == tflite::BuiltinOperator_CUSTOM
+        || registration.builtin_code == kTfLiteBuiltinDelegate) {
+        return registration.custom_name ? registration.custom_name : "";
+    }
+
+    return tflite::EnumNameBuiltinOperator(static_cast<tflite::BuiltinOperator>(registration.builtin_code));
+}
+
+void collect_profile_events(RuneCoralContext *context) {
+    context->profileEvents.clear();
+
+    auto events = context->profiler->GetProfileEvents();
+    if (events.empty()) {
+        return;
+    }
+
+    uint64_t start = events.front()->begin_timestamp_us;
+    for (const auto *event : events) {
+        start = std::min(start, event->begin_timestamp_us);
+    }
+
+    for (const auto *event : events) {
+        if (event->event_type != tflite::Profiler::EventType::OPERATOR_INVOKE_EVENT) {
+            continue;
+        }
+
+        const int node_index = static_cast<int>(event->event_metadata);
+        const int subgraph_index = static_cast<int>(event->extra_event_metadata);
+        const auto *subgraph = context->interpreter->subgraph(subgraph_index);
+        if (!subgraph) {
+            continue;
+        }
+        const auto *nodeAndRegistration = subgraph->node_and_registration(node_index);
+        if (!nodeAndRegistration) {
+            continue;
+        }
+        const auto &registration = nodeAndRegistration->second;
+
+        RuneCoralProfileEvent result;
+        result.op_name = op_name(registration);
+        result.builtin_code = registration.builtin_code;
+        result.subgraph_index = subgraph_index;
+        result.node_index = node_index;
+        result.delegated = registration.builtin_code == kTfLiteBuiltinDelegate;
+        result.start_us = event->begin_timestamp_us - start;
+        result.duration_us = event->end_timestamp_us - event->begin_timestamp_us;
+        context->profileEvents.push_back(result);
+    }
+}
+
+void inference_enable_profiling(RuneCoralContext *context) {
+    if (!context || context->profiler) {
+        return;
+    }
+
+    const auto& executionPlan = context->interpreter->execution_plan();
+    context->profiler.reset(new tflite::profiling::BufferedProfiler(
+        std::max<uint32_t>(executionPlan.size(), 64), /*allow_dynamic_buffer_increase=*/true));
+    context->interpreter->SetProfiler(context->profiler.get());
+}
+
+void inference_disable_profiling(RuneCoralContext *context) {
+    if (!context) {
+        return;
+    }
+
+    context->interpreter->SetProfiler(nullptr);
+    context->profiler.reset();
+    context->profileEvents.clear();
+}
+
+size_t inference_profile(const RuneCoralContext * const context, const RuneCoralProfileEvent **events) {
+    if (!context) {
+        *events = nullptr;
+        return 0;
+    }
+
+    *events = context->profileEvents.data();
+    return context->profileEvents.size();
+}
+
 RuneCoralInferenceResult infer(RuneCoralContext *context, const RuneCoralTensor *inputs, size_t num_inputs,
                                RuneCoralTensor *outputs, size_t num_outputs) {
     // Validity checks
@@ -184,6 +704,22 @@ RuneCoralInferenceResult infer(RuneCoralContext *context, const RuneCoralTensor
         return RuneCoralInferenceResult__Error;
     }
 
+    // We copy each tensor's full size, so the caller's buffers need to match
+    if (num_inputs != context->interpreter->inputs().size() ||
+        num_outputs != context->interpreter->outputs().size()) {
+        return RuneCoralInferenceResult__Error;
+    }
+    for (size_t i = 0; i < num_inputs; i++) {
+        if (inputs[i].bytes != context->interpreter->input_tensor(i)->bytes) {
+            return RuneCoralInferenceResult__Error;
+        }
+    }
+    for (size_t i = 0; i < num_outputs; i++) {
+        if (outputs[i].bytes != context->interpreter->output_tensor(i)->bytes) {
+            return RuneCoralInferenceResult__Error;
+        }
+    }
+
     // Feed inputs to the interpreter
     for (size_t i = 0; i < num_inputs; i++) {
         auto tfTensor = context->interpreter->input_tensor(i);
@@ -192,7 +728,18 @@ RuneCoralInferenceResult infer(RuneCoralContext *context, const RuneCoralTensor
                   reinterpret_cast<char*>(tfTensor->data.data));
     }
 
+    if (context->profiler) {
+        context->profiler->Reset();
+        context->profiler->StartProfiling();
+    }
+
     auto inferenceResult = context->interpreter->Invoke();
+
+    if (context->profiler) {
+        context->profiler->StopProfiling();
+        collect_profile_events(context);
+    }
+
     if (inferenceResult == kTfLiteOk) {
         //Collect outputs
         for (size_t i = 0; i < num_outputs; i++) {
@@ -205,3 +752,254 @@ RuneCoralInferenceResult infer(RuneCoralContext *context, const RuneCoralTensor
 
     return static_cast<RuneCoralInferenceResult>(inferenceResult);
 }
+
+template <typename T>
+const T *vector_data(const flatbuffers::Vector<T> *vector) {
+    return vector ? vector->data() : nullptr;
+}
+
+template <typename T>
+size_t vector_size(const flatbuffers::Vector<T> *vector) {
+    return vector ? vector->size() : 0;
+}
+
+RuneCoralGraphTensor to_graph_tensor(const tflite::Model &model, const tflite::Tensor &tensor) {
+    RuneCoralGraphTensor result{};
+    result.name = tensor.name() ? tensor.name()->c_str() : "";
+
+    TfLiteType type = kTfLiteNoType;
+    tflite::ConvertTensorType(tensor.type(), &type, tflite::DefaultErrorReporter());
+    result.type = static_cast<RuneCoralElementType>(type);
+
+    result.shape = vector_data(tensor.shape());
+    result.rank = vector_size(tensor.shape());
+
+    if (const auto *quantization = tensor.quantization()) {
+        result.quantization.scale = vector_data(quantization->scale());
+        result.quantization.zero_point = vector_data(quantization->zero_point());
+        result.quantization.count = std::min(vector_size(quantization->scale()),
+                                             vector_size(quantization->zero_point()));
+        result.quantization.quantized_dimension = quantization->quantized_dimension();
+    }
+
+    result.buffer = tensor.buffer();
+    if (tensor.buffer() < vector_size(model.buffers())) {
+        const auto *buffer = model.buffers()->Get(tensor.buffer());
+        result.buffer_size = buffer ? vector_size(buffer->data()) : 0;
+    }
+
+    result.is_variable = tensor.is_variable();
+    return result;
+}
+
+RuneCoralOperator to_operator(const tflite::Model &model, const tflite::Operator &op) {
+    RuneCoralOperator result{};
+    result.op_name = "";
+
+    if (op.opcode_index() < vector_size(model.operator_codes())) {
+        const auto *opcode = model.operator_codes()->Get(op.opcode_index());
+        const auto builtinCode = tflite::GetBuiltinCode(opcode);
+        result.builtin_code = builtinCode;
+        result.op_name = tflite::EnumNameBuiltinOperator(builtinCode);
+        if (builtinCode == tflite::BuiltinOperator_CUSTOM && opcode->custom_code()) {
+            result.custom_name = opcode->custom_code()->c_str();
+        }
+        result.version = opcode->version();
+    }
+
+    result.inputs = vector_data(op.inputs());
+    result.num_inputs = vector_size(op.inputs());
+    result.outputs = vector_data(op.outputs());
+    result.num_outputs = vector_size(op.outputs());
+    return result;
+}
+
+void describe_graph(RuneCoralModel *model) {
+    const auto *flatbuffer = model->model->GetModel();
+    const auto *subgraphs = flatbuffer->subgraphs();
+    const size_t numSubgraphs = vector_size(subgraphs);
+
+    model->tensors.resize(numSubgraphs);
+    model->operators.resize(numSubgraphs);
+
+    for (size_t i = 0; i < numSubgraphs; i++) {
+        const auto *subgraph = subgraphs->Get(i);
+        auto &tensors = model->tensors[i];
+        auto &operators = model->operators[i];
+
+        for (size_t j = 0; j < vector_size(subgraph->tensors()); j++) {
+            tensors.push_back(to_graph_tensor(*flatbuffer, *subgraph->tensors()->Get(j)));
+        }
+
+        for (size_t j = 0; j < vector_size(subgraph->operators()); j++) {
+            operators.push_back(to_operator(*flatbuffer, *subgraph->operators()->Get(j)));
+        }
+
+        RuneCoralSubgraph result{};
+        result.name = subgraph->name() ? subgraph->name()->c_str() : "";
+        result.tensors = tensors.data();
+        result.num_tensors = tensors.size();
+        result.operators = operators.data();
+        result.num_operators = operators.size();
+        result.inputs = vector_data(subgraph->inputs());
+        result.num_inputs = vector_size(subgraph->inputs());
+        result.outputs = vector_data(subgraph->outputs());
+        result.num_outputs = vector_size(subgraph->outputs());
+        model->subgraphs.push_back(result);
+    }
+}
+
+bool buffer_in_range(const tflite::Model &model, uint32_t buffer) {
+    return buffer < vector_size(model.buffers());
+}
+
+RuneCoralVerifyResult verify_model(const void *model, size_t model_len, RuneCoralModelInfo *info) {
+    if (!(model && info)) {
+        return RuneCoralVerifyResult__Malformed;
+    }
+
+    *info = RuneCoralModelInfo{};
+    info->size = model_len;
+    info->supported_schema_version = TFLITE_SCHEMA_VERSION;
+
+    // The root table's offset, followed by the file identifier
+    const auto *bytes = static_cast<const uint8_t *>(model);
+    if (model_len < sizeof(flatbuffers::uoffset_t) + flatbuffers::kFileIdentifierLength) {
+        return RuneCoralVerifyResult__Truncated;
+    }
+    if (!tflite::ModelBufferHasIdentifier(bytes)) {
+        return RuneCoralVerifyResult__BadIdentifier;
+    }
+    if (flatbuffers::ReadScalar<flatbuffers::uoffset_t>(bytes) >= model_len) {
+        return RuneCoralVerifyResult__Truncated;
+    }
+
+    flatbuffers::Verifier verifier(bytes, model_len);
+    if (!tflite::VerifyModelBuffer(verifier)) {
+        return RuneCoralVerifyResult__Malformed;
+    }
+
+    const auto *flatbuffer = tflite::GetModel(bytes);
+    info->schema_version = flatbuffer->version();
+    info->num_subgraphs = vector_size(flatbuffer->subgraphs());
+    info->num_operator_codes = vector_size(flatbuffer->operator_codes());
+    info->num_buffers = vector_size(flatbuffer->buffers());
+
+    if (flatbuffer->version() != TFLITE_SCHEMA_VERSION) {
+        return RuneCoralVerifyResult__SchemaVersionMismatch;
+    }
+
+    for (size_t i = 0; i < info->num_subgraphs; i++) {
+        const auto *tensors = flatbuffer->subgraphs()->Get(i)->tensors();
+        for (size_t j = 0; j < vector_size(tensors); j++) {
+            if (!buffer_in_range(*flatbuffer, tensors->Get(j)->buffer())) {
+                return RuneCoralVerifyResult__BufferOutOfRange;
+            }
+        }
+    }
+
+    const auto *metadata = flatbuffer->metadata();
+    for (size_t i = 0; i < vector_size(metadata); i++) {
+        if (!buffer_in_range(*flatbuffer, metadata->Get(i)->buffer())) {
+            return RuneCoralVerifyResult__BufferOutOfRange;
+        }
+    }
+
+    return RuneCoralVerifyResult__Ok;
+}
+
+RuneCoralLoadResult create_model(const void *model, size_t model_len, RuneCoralModel **modelOut) {
+    if (!(model && modelOut)) {
+        return RuneCoralLoadResult__InternalError;
+    }
+
+    RuneCoralModel *result = new RuneCoralModel{(const char *)model, model_len};
+
+    result->model = tflite::FlatBufferModel::VerifyAndBuildFromBuffer(
+        result->model_buffer.data(),
+        result->model_buffer.size()
+    );
+
+    if (!result->model) {
+        LOG_E("Unable to create a TFlite Model from the buffer that is passed");
+        delete result;
+        *modelOut = nullptr;
+        return RuneCoralLoadResult__InternalError;
+    }
+
+    describe_graph(result);
+    *modelOut = result;
+    return RuneCoralLoadResult__Ok;
+}
+
+size_t model_subgraphs(const RuneCoralModel * const model, const RuneCoralSubgraph **subgraphs) {
+    if (!model) {
+        *subgraphs = nullptr;
+        return 0;
+    }
+
+    *subgraphs = model->subgraphs.data();
+    return model->subgraphs.size();
+}
+
+size_t model_unresolved_ops(const RuneCoralModel * const model, const RuneCoralOpResolver *resolver,
+                            RuneCoralOpCode *ops, size_t capacity) {
+    if (!model) {
+        return 0;
+    }
+
+    std::vector<RuneCoralOpCode> unresolved;
+    if (resolver) {
+        unresolved = find_unresolved_ops(*model->model->GetModel(), resolver->resolver);
+    } else {
+        unresolved = find_unresolved_ops(*model->model->GetModel(), DefaultOpResolver());
+    }
+    std::copy_n(unresolved.begin(), std::min(capacity, unresolved.size()), ops);
+
+    return unresolved.size();
+}
+
+size_t model_metadata(const RuneCoralModel * const model, const char *name, const void **data) {
+    *data = nullptr;
+    if (!(model && name)) {
+        return 0;
+    }
+
+    const auto *flatbuffer = model->model->GetModel();
+    const auto *metadata = flatbuffer->metadata();
+
+    for (size_t i = 0; i < vector_size(metadata); i++) {
+        const auto *entry = metadata->Get(i);
+        if (!entry->name() || std::strcmp(entry->name()->c_str(), name) != 0) {
+            continue;
+        }
+
+        if (entry->buffer() >= vector_size(flatbuffer->buffers())) {
+            return 0;
+        }
+
+        const auto *buffer = flatbuffer->buffers()->Get(entry->buffer());
+        if (!buffer || !buffer->data()) {
+            return 0;
+        }
+
+        *data = buffer->data()->data();
+        return buffer->data()->size();
+    }
+
+    return 0;
+}
+
+size_t model_buffer(const RuneCoralModel * const model, const void **data) {
+    if (!model) {
+        *data = nullptr;
+        return 0;
+    }
+
+    *data = model->model_buffer.data();
+    return model->model_buffer.size();
+}
+
+void destroy_model(RuneCoralModel *model) {
+    delete model;
+}
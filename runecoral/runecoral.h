@@ -1,9 +1,18 @@
 #pragma once
 
 #include <stddef.h>
+#include <stdint.h>
 
 extern const char *RUNE_CORAL_MIME_TYPE__TFLITE;
 
+// The librunecoral release this header is from
+#define RUNECORAL_VERSION "0.3.12"
+
+// Incremented whenever a change to this header breaks binary compatibility
+// (e.g. adding a field to a struct), so callers can detect when they are using
+// a librunecoral built from a different header
+#define RUNECORAL_ABI_VERSION 3
+
 // These types match the TfLiteType from tensorflow.
 // When modifying these types, only add to this enum
 typedef enum {
@@ -36,45 +45,90 @@ typedef struct {
   const int *shape;
   // How many dimensions are there?
   size_t rank;
+  // The number of bytes pointed to by data.
+  size_t bytes;
 } RuneCoralTensor;
 
 typedef struct RuneCoralContext RuneCoralContext;
 
 typedef enum {
   RuneCoralLoadResult__Ok = 0,
+  // No longer returned (see RuneCoralLoadResult__UnsupportedFormat), but kept
+  // so the other values don't change
   RuneCoralLoadResult__IncorrectMimeType,
   RuneCoralLoadResult__InternalError,
+  // The model uses operators which can't be resolved (see model_unresolved_ops())
+  RuneCoralLoadResult__UnsupportedOperator,
+  // The mimetype isn't supported, or no mimetype was given and the model's
+  // format couldn't be detected (see supported_mimetypes())
+  RuneCoralLoadResult__UnsupportedFormat,
+  // The model was compiled with edgetpu_compiler, but Edgetpu acceleration
+  // wasn't requested or no Edge TPU is available
+  RuneCoralLoadResult__EdgetpuRequired,
 } RuneCoralLoadResult;
 
+// Fill mimetypes with up to len of the model formats librunecoral can load,
+// returning how many there are in total. Aliases aren't included.
+size_t supported_mimetypes(const char **mimetypes, size_t len);
+
+// Get the canonical mimetype for a supported mimetype or one of its aliases
+// (ignoring case). When mimetype is NULL or empty, the format is detected from
+// the model's contents instead. Returns NULL if the format isn't supported.
+const char *resolve_mimetype(const char *mimetype, const void *model, size_t model_len);
+
 
 typedef enum {
   RuneCoralAccelerationBackend__None = 0,
   RuneCoralAccelerationBackend__Edgetpu = 1 << 0,
   RuneCoralAccelerationBackend__Gpu = 1 << 1,
+  // Optimized floating point kernels for the CPU
+  RuneCoralAccelerationBackend__Xnnpack = 1 << 2,
 } RuneCoralAccelerationBackend;
 
 // Returns an int with all the backends that are available
 int availableAccelerationBackends();
 
-// Load a model using its "mimetype" to figure out what format the model is in
-// Only "application/tflite-model" is accepted at this time.
-// And then create an interpreter for the model to be interpreted
-// Also verifies if the input and output tensors match that of model
-RuneCoralLoadResult create_inference_context(const char *mimetype, const void *model, size_t model_len,
-                                             const RuneCoralAccelerationBackend backend,
-                                             RuneCoralContext **inferenceContext);
+// The RUNECORAL_VERSION librunecoral was built with
+const char *runecoral_version();
 
-// Returns the number of opcodes currently used
-size_t inference_opcount(const RuneCoralContext * const inferenceContext);
+// The RUNECORAL_ABI_VERSION librunecoral was built with
+int runecoral_abi_version();
 
-// Return the number of input tensors of the current inference context, and update tensors to point to them
-size_t inference_inputs(const RuneCoralContext * const inferenceContext, const RuneCoralTensor ** tensors);
+// sizeof(RuneCoralTensor) when librunecoral was built, as a last line of
+// defence against layout changes which didn't bump RUNECORAL_ABI_VERSION
+size_t runecoral_sizeof_tensor();
 
-// Return the number of output tensors of the current inference context, and update tensors to point to them
-size_t inference_outputs(const RuneCoralContext * const inferenceContext, const RuneCoralTensor ** tensors);
+// The version of TensorFlow Lite librunecoral was built with
+const char *runecoral_tflite_version();
 
-// frees all the resources allocated for a context
-void destroy_inference_context(RuneCoralContext *inferenceContext);
+// The version of the delegate used by an acceleration backend, or NULL if
+// librunecoral was built without it
+const char *runecoral_delegate_version(RuneCoralAccelerationBackend backend);
+
+// How an Edge TPU is connected (these match libedgetpu's edgetpu_device_type)
+typedef enum {
+  RuneCoralEdgetpuDeviceType__Pci = 0,
+  RuneCoralEdgetpuDeviceType__Usb = 1,
+} RuneCoralEdgetpuDeviceType;
+
+// An Edge TPU connected to this machine
+typedef struct {
+  RuneCoralEdgetpuDeviceType type;
+  // The device's path (e.g. "/dev/apex_0" or "/sys/bus/usb/devices/2-1")
+  char path[256];
+} RuneCoralEdgetpuDevice;
+
+// Fill devices with up to capacity of the Edge TPUs connected to this machine,
+// returning how many there are in total. This is always 0 when librunecoral
+// was built without EdgeTPU acceleration.
+size_t runecoral_edgetpu_devices(RuneCoralEdgetpuDevice *devices, size_t capacity);
+
+// An option for libedgetpu's delegate (e.g. "Performance" = "Max"), the same
+// as libedgetpu's edgetpu_option
+typedef struct {
+  const char *name;
+  const char *value;
+} RuneCoralEdgetpuOption;
 
 // Modeled after TfLiteStatus for now
 typedef enum {
@@ -90,7 +144,282 @@ typedef enum {
   RuneCoralInferenceResult__ApplicationError = 3
 } RuneCoralInferenceResult;
 
+// Resolves the operators used by a model to their implementations
+typedef struct RuneCoralOpResolver RuneCoralOpResolver;
+
+// Callbacks for a custom operator that is implemented outside of librunecoral
+typedef struct {
+  // Opaque pointer that is passed to each callback
+  void *user_data;
+  // Validate the node's tensors once their shapes are known. May be NULL.
+  RuneCoralInferenceResult (*prepare)(void *user_data,
+                                      const RuneCoralTensor *inputs, size_t num_inputs,
+                                      const RuneCoralTensor *outputs, size_t num_outputs);
+  // Run the operator, reading from inputs and writing to outputs
+  RuneCoralInferenceResult (*invoke)(void *user_data,
+                                     const RuneCoralTensor *inputs, size_t num_inputs,
+                                     RuneCoralTensor *outputs, size_t num_outputs);
+  // Clean up user_data when the op resolver is destroyed. May be NULL.
+  void (*destroy)(void *user_data);
+} RuneCoralCustomOp;
+
+// Create an op resolver which knows about all builtin operators (or just the
+// selected ones, when librunecoral was built with SELECTED_OPS)
+RuneCoralOpResolver *create_op_resolver();
+
+// Register a custom operator, returning zero on success. The resolver takes
+// ownership of the operator's user_data, even when registration fails.
+int op_resolver_add_custom(RuneCoralOpResolver *resolver, const char *name, int version, RuneCoralCustomOp op);
+
+// frees all the resources allocated for an op resolver. This must only be
+// done once every context created with it has been destroyed.
+void destroy_op_resolver(RuneCoralOpResolver *resolver);
+
+// Settings used when creating an inference context
+typedef struct {
+  RuneCoralAccelerationBackend backend;
+  // Resolves the model's operators. Only builtin operators are available when NULL.
+  const RuneCoralOpResolver *resolver;
+  // Keep every intermediate tensor around after inference instead of reusing
+  // their memory (see inference_tensors()). This uses a lot more memory.
+  int preserve_all_tensors;
+  // Which Edge TPU to use with RuneCoralAccelerationBackend__Edgetpu (see
+  // runecoral_edgetpu_devices()). The first one is used when NULL.
+  const RuneCoralEdgetpuDevice *edgetpu_device;
+  // Options passed to the Edge TPU delegate as-is. May be NULL when
+  // num_edgetpu_options is 0.
+  const RuneCoralEdgetpuOption *edgetpu_options;
+  size_t num_edgetpu_options;
+} RuneCoralContextOptions;
+
+// Load a model using its "mimetype" to figure out what format the model is in
+// (see resolve_mimetype()). Only TensorFlow Lite models are supported at this time.
+// And then create an interpreter for the model to be interpreted
+// Also verifies if the input and output tensors match that of model
+RuneCoralLoadResult create_inference_context(const char *mimetype, const void *model, size_t model_len,
+                                             const RuneCoralAccelerationBackend backend,
+                                             RuneCoralContext **inferenceContext);
+
+// The same as create_inference_context(), but with more control over how the
+// interpreter is created
+RuneCoralLoadResult create_inference_context_with_options(const char *mimetype, const void *model, size_t model_len,
+                                                          const RuneCoralContextOptions *options,
+                                                          RuneCoralContext **inferenceContext);
+
+// Returns the number of opcodes currently used
+size_t inference_opcount(const RuneCoralContext * const inferenceContext);
+
+// Return the number of input tensors of the current inference context, and update tensors to point to them
+size_t inference_inputs(const RuneCoralContext * const inferenceContext, const RuneCoralTensor ** tensors);
+
+// Return the number of output tensors of the current inference context, and update tensors to point to them
+size_t inference_outputs(const RuneCoralContext * const inferenceContext, const RuneCoralTensor ** tensors);
+
+// frees all the resources allocated for a context
+void destroy_inference_context(RuneCoralContext *inferenceContext);
+
 // Run inference on the model with the inputs provided and collect the outputs
+// Each tensor's bytes must match the interpreter's tensor exactly, otherwise
+// RuneCoralInferenceResult__Error is returned without running the model.
 RuneCoralInferenceResult infer(RuneCoralContext *context,
                                const RuneCoralTensor *inputs, size_t num_inputs,
                                RuneCoralTensor *outputs, size_t num_outputs);
+
+// Timing information for a single operator invocation, recorded while
+// profiling is enabled
+typedef struct {
+  // The operator's name (e.g. "CONV_2D"), or the custom op/delegate name
+  const char *op_name;
+  // The operator's tflite::BuiltinOperator code
+  int builtin_code;
+  // Which subgraph does the node belong to?
+  int subgraph_index;
+  // The node's index within its subgraph
+  int node_index;
+  // Non-zero when the node was executed by a delegate instead of the CPU
+  int delegated;
+  // When the operator started, in microseconds since the start of the run
+  uint64_t start_us;
+  // How long the operator took to execute, in microseconds
+  uint64_t duration_us;
+} RuneCoralProfileEvent;
+
+// Record per-operator timings for every subsequent call to infer()
+void inference_enable_profiling(RuneCoralContext *context);
+
+// Stop recording per-operator timings
+void inference_disable_profiling(RuneCoralContext *context);
+
+// Return the number of events recorded during the most recent call to infer(), and update events to point to them
+size_t inference_profile(const RuneCoralContext * const context, const RuneCoralProfileEvent **events);
+
+// Reset every variable tensor (e.g. a recurrent model's state) to its initial value
+RuneCoralInferenceResult inference_reset_variable_tensors(RuneCoralContext *context);
+
+// Return the number of variable tensors, writing up to capacity of them to
+// tensors. Each tensor's data points to the interpreter's copy, so it can be
+// read or written to save and restore state between calls to infer().
+size_t inference_variable_tensors(const RuneCoralContext * const context, RuneCoralTensor *tensors, size_t capacity);
+
+// Return the number of tensors in the interpreter's primary subgraph, writing up
+// to capacity of them to tensors. Intermediate tensors are only guaranteed to
+// contain the values from the most recent call to infer() when the context was
+// created with preserve_all_tensors.
+size_t inference_tensors(const RuneCoralContext * const context, RuneCoralTensor *tensors, size_t capacity);
+
+// An estimate of the memory used by an inference context, in bytes
+typedef struct {
+  // The context's copy of the model, including constant tensors like weights
+  size_t model;
+  // The part of the tensor arena used by intermediate tensors. This memory is
+  // reused by operators as inference progresses.
+  size_t arena;
+  // The part of the tensor arena used by tensors which live as long as the
+  // context (e.g. variable tensors)
+  size_t persistent_arena;
+  // Tensors allocated separately because their size is only known at runtime
+  size_t dynamic;
+  // Tensor buffers owned by delegates. This doesn't include memory delegates
+  // allocate internally, so it is zero for most delegates.
+  size_t delegate;
+} RuneCoralMemoryStats;
+
+// Measure the memory used by a context's model and tensors
+void inference_memory_stats(const RuneCoralContext * const context, RuneCoralMemoryStats *stats);
+
+// A model that has been loaded and verified, but not turned into an interpreter
+typedef struct RuneCoralModel RuneCoralModel;
+
+// Affine quantization parameters, where real_value = scale * (quantized_value - zero_point)
+typedef struct {
+  // One scale per channel (or a single scale when quantizing per-tensor)
+  const float *scale;
+  // One zero point per channel (or a single zero point when quantizing per-tensor)
+  const int64_t *zero_point;
+  // How many scale/zero point pairs are there?
+  size_t count;
+  // The dimension that per-channel quantization is applied along
+  int quantized_dimension;
+} RuneCoralQuantization;
+
+// A tensor as it is described by the model
+typedef struct {
+  // C style string to the tensor's name
+  const char *name;
+  // What type of elements does this tensor contain?
+  RuneCoralElementType type;
+  // An array containing the length of each of the tensor's dimensions.
+  const int *shape;
+  // How many dimensions are there?
+  size_t rank;
+  // The tensor's quantization parameters (count is 0 when it isn't quantized)
+  RuneCoralQuantization quantization;
+  // Index of the model buffer backing this tensor (0 means no constant data)
+  int buffer;
+  // The number of bytes of constant data stored in the model for this tensor
+  size_t buffer_size;
+  // Non-zero when the tensor holds state between inference runs
+  int is_variable;
+} RuneCoralGraphTensor;
+
+// A single node in a subgraph
+typedef struct {
+  // The operator's tflite::BuiltinOperator code
+  int builtin_code;
+  // The builtin operator's name (e.g. "CONV_2D")
+  const char *op_name;
+  // The custom operator's name, or NULL for builtin operators
+  const char *custom_name;
+  // Which version of the operator is used?
+  int version;
+  // Indices of the subgraph tensors used as inputs (-1 for omitted optional inputs)
+  const int *inputs;
+  size_t num_inputs;
+  // Indices of the subgraph tensors used as outputs
+  const int *outputs;
+  size_t num_outputs;
+} RuneCoralOperator;
+
+typedef struct {
+  // C style string to the subgraph's name
+  const char *name;
+  const RuneCoralGraphTensor *tensors;
+  size_t num_tensors;
+  // The operators, in the order they are stored in the model
+  const RuneCoralOperator *operators;
+  size_t num_operators;
+  // Indices of the tensors used as the subgraph's inputs
+  const int *inputs;
+  size_t num_inputs;
+  // Indices of the tensors used as the subgraph's outputs
+  const int *outputs;
+  size_t num_outputs;
+} RuneCoralSubgraph;
+
+// An operator (and the version of it) used by a model
+typedef struct {
+  // The operator's tflite::BuiltinOperator code
+  int builtin_code;
+  // The builtin operator's name (e.g. "CONV_2D")
+  const char *op_name;
+  // The custom operator's name, or NULL for builtin operators
+  const char *custom_name;
+  int version;
+} RuneCoralOpCode;
+
+typedef enum {
+  RuneCoralVerifyResult__Ok = 0,
+  // The buffer ends before the model does
+  RuneCoralVerifyResult__Truncated,
+  // The buffer doesn't have the "TFL3" file identifier
+  RuneCoralVerifyResult__BadIdentifier,
+  // The model was written using a schema version this library can't read
+  RuneCoralVerifyResult__SchemaVersionMismatch,
+  // A tensor or metadata entry refers to a buffer that doesn't exist
+  RuneCoralVerifyResult__BufferOutOfRange,
+  // The flatbuffer failed verification for some other reason
+  RuneCoralVerifyResult__Malformed,
+} RuneCoralVerifyResult;
+
+// Information about a model, as determined by verify_model()
+typedef struct {
+  // The number of bytes in the buffer
+  size_t size;
+  // The tflite schema version the model was written with
+  uint32_t schema_version;
+  // The schema version this library supports
+  uint32_t supported_schema_version;
+  size_t num_subgraphs;
+  size_t num_operator_codes;
+  size_t num_buffers;
+} RuneCoralModelInfo;
+
+// Check that a buffer contains a valid TensorFlow Lite model without loading
+// it, filling in as much of info as possible. The buffer isn't copied or
+// retained.
+RuneCoralVerifyResult verify_model(const void *model, size_t model_len, RuneCoralModelInfo *info);
+
+// Load and verify a TensorFlow Lite model without creating an interpreter for it
+RuneCoralLoadResult create_model(const void *model, size_t model_len, RuneCoralModel **modelOut);
+
+// Return the number of subgraphs in the model, and update subgraphs to point to them
+size_t model_subgraphs(const RuneCoralModel * const model, const RuneCoralSubgraph **subgraphs);
+
+// Return the number of operators used by the model that resolver can't
+// resolve, writing up to capacity of them to ops. Only builtin operators are
+// considered when resolver is NULL.
+size_t model_unresolved_ops(const RuneCoralModel * const model, const RuneCoralOpResolver *resolver,
+                            RuneCoralOpCode *ops, size_t capacity);
+
+// Return the length of the model's metadata buffer with the given name (e.g.
+// "TFLITE_METADATA"), and update data to point to it. Returns 0 and sets data
+// to NULL when the model doesn't have that metadata.
+size_t model_metadata(const RuneCoralModel * const model, const char *name, const void **data);
+
+// Return the length of the buffer the model was loaded from, and update data to
+// point to it. This includes anything appended to the flatbuffer, like the zip
+// archive of associated files used by TensorFlow Lite metadata.
+size_t model_buffer(const RuneCoralModel * const model, const void **data);
+
+// frees all the resources allocated for a model
+void destroy_model(RuneCoralModel *model);
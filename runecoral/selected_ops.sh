@@ -0,0 +1,94 @@
+#!/bin/bash
+# Helpers for building librunecoral with only the operators a set of models need.
+#
+# Usage:
+#   selected_ops.sh --stage <entries>   Copy the models and operator names in
+#                                       <entries> (a comma-separated list of
+#                                       .tflite files and builtin operator names,
+#                                       e.g. "model.tflite,CONV_2D") to
+#                                       runecoral/selected_ops/ for Bazel to use
+#   selected_ops.sh --generate <register.cc> [ops.txt]
+#                                       Print C++ which registers each of the
+#                                       builtin operators listed in ops.txt,
+#                                       using the same kernels and versions
+#                                       as TensorFlow Lite's register.cc
+
+set -euo pipefail
+
+SCRIPT_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+STAGING_DIR="$SCRIPT_DIR/selected_ops"
+
+stage() {
+    rm -rf "$STAGING_DIR"
+    mkdir -p "$STAGING_DIR"
+    touch "$STAGING_DIR/ops.txt"
+
+    IFS=',' read -ra entries <<< "$1"
+    for entry in "${entries[@]}"; do
+        entry="$(echo "$entry" | xargs)"
+        if [ -z "$entry" ]; then
+            continue
+        elif [[ "$entry" == *.tflite ]]; then
+            if [ ! -f "$entry" ]; then
+                echo "Unable to find the model \"$entry\"" >&2
+                exit 1
+            fi
+            cp "$entry" "$STAGING_DIR/$(basename "$entry" .tflite)-$(md5sum < "$entry" | cut -c1-8).tflite"
+        elif [[ "$entry" =~ ^[A-Z0-9_]+$ ]]; then
+            echo "$entry" >> "$STAGING_DIR/ops.txt"
+        else
+            echo "\"$entry\" is neither a .tflite file nor a builtin operator name (e.g. CONV_2D)" >&2
+            exit 1
+        fi
+    done
+}
+
+# Print each AddBuiltin(...) call in TensorFlow Lite's register.cc on a single
+# line, so the kernel and supported versions of an operator can be looked up.
+registrations() {
+    awk '
+        /AddBuiltin\(/ { call = "" }
+        call != "" || /AddBuiltin\(/ {
+            sub(/^[ \t]+/, "")
+            call = call " " $0
+            if (call ~ /\);$/) {
+                gsub(/\( /, "(", call)
+                print substr(call, 2)
+                call = ""
+            }
+        }
+    ' "$1"
+}
+
+generate() {
+    local register_cc="$1"
+    local calls
+    calls="$(registrations "$register_cc")"
+
+    echo '#include "tensorflow/lite/kernels/builtin_op_kernels.h"'
+    echo '#include "tensorflow/lite/mutable_op_resolver.h"'
+    echo
+    echo 'namespace runecoral_named_ops {'
+    echo 'using namespace ::tflite;'
+    echo 'using namespace ::tflite::ops::builtin;'
+    echo
+    echo 'void RegisterSelectedOps(::tflite::MutableOpResolver *resolver) {'
+    if [ $# -gt 1 ]; then
+        for op in $(sort -u "$2"); do
+            call="$(grep -F "AddBuiltin(BuiltinOperator_$op," <<< "$calls" || true)"
+            if [ -z "$call" ]; then
+                echo "\"$op\" isn't a builtin operator registered in $register_cc" >&2
+                exit 1
+            fi
+            echo "  resolver->$call"
+        done
+    fi
+    echo '}'
+    echo '}  // namespace runecoral_named_ops'
+}
+
+case "${1:-}" in
+    --stage) stage "${2:-}" ;;
+    --generate) shift; generate "$@" ;;
+    *) echo "Usage: $0 --stage <entries> | --generate <register.cc> [ops.txt]" >&2; exit 1 ;;
+esac
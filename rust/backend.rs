@@ -0,0 +1,397 @@
+//! Types shared by every inference backend.
+
+use crate::{
+    ffi, EdgeTpuDevice, EdgeTpuSelector, Error, Tensor, TensorDescriptor, TensorMismatch, TensorMut,
+};
+use bitflags::bitflags;
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Debug, Formatter},
+};
+
+/// An engine which can run inference on a model.
+///
+/// This lets code which runs models work with any engine, whether that is
+/// TensorFlow Lite via an [`InferenceContext`][crate::InferenceContext], a
+/// [`MockContext`][crate::mock::MockContext] in tests, or something else
+/// entirely.
+pub trait Backend: Debug + Send {
+    fn inputs(&self) -> Vec<TensorDescriptor<'_>>;
+
+    fn outputs(&self) -> Vec<TensorDescriptor<'_>>;
+
+    fn infer(
+        &mut self,
+        inputs: &[Tensor<'_>],
+        outputs: &mut [TensorMut<'_>],
+    ) -> Result<(), InferError>;
+
+    /// What this backend is able to do.
+    fn capabilities(&self) -> Capabilities;
+}
+
+impl<B: Backend + ?Sized> Backend for Box<B> {
+    fn inputs(&self) -> Vec<TensorDescriptor<'_>> {
+        (**self).inputs()
+    }
+
+    fn outputs(&self) -> Vec<TensorDescriptor<'_>> {
+        (**self).outputs()
+    }
+
+    fn infer(
+        &mut self,
+        inputs: &[Tensor<'_>],
+        outputs: &mut [TensorMut<'_>],
+    ) -> Result<(), InferError> {
+        (**self).infer(inputs, outputs)
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        (**self).capabilities()
+    }
+}
+
+/// The features supported by a [`Backend`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Capabilities {
+    /// The hardware being used to accelerate inference.
+    pub acceleration_backend: AccelerationBackend,
+    /// Can per-operator timings be recorded (e.g. with
+    /// [`InferenceContext::profile()`][crate::InferenceContext::profile])?
+    pub profiling: bool,
+    /// Can the model's variable tensors be saved and restored (e.g. with
+    /// [`InferenceContext::save_state()`][crate::InferenceContext::save_state])?
+    pub state: bool,
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Capabilities {
+            acceleration_backend: AccelerationBackend::NONE,
+            profiling: false,
+            state: false,
+        }
+    }
+}
+
+type LoadFn =
+    Box<dyn Fn(&[u8], AccelerationBackend) -> Result<Box<dyn Backend>, Error> + Send + Sync>;
+
+/// A set of [`Backend`]s, keyed by the mimetype of the models they can load.
+///
+/// The default registry uses TensorFlow Lite to load
+/// [`mimetype()`][crate::mimetype] models.
+///
+/// ```rust,no_run
+/// use hotg_runecoral::{AccelerationBackend, BackendRegistry};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let registry = BackendRegistry::default();
+/// let model = std::fs::read("model.tflite")?;
+///
+/// let backend = registry.load("application/tflite-model", &model, AccelerationBackend::NONE)?;
+/// println!("{:?}", backend.capabilities());
+/// # Ok(())
+/// # }
+/// ```
+pub struct BackendRegistry {
+    loaders: BTreeMap<String, LoadFn>,
+}
+
+impl BackendRegistry {
+    /// Create an empty [`BackendRegistry`].
+    pub fn new() -> Self {
+        BackendRegistry {
+            loaders: BTreeMap::new(),
+        }
+    }
+
+    /// Use `load` to create a [`Backend`] for models with this mimetype,
+    /// replacing any existing loader.
+    pub fn register<F>(&mut self, mimetype: &str, load: F) -> &mut Self
+    where
+        F: Fn(&[u8], AccelerationBackend) -> Result<Box<dyn Backend>, Error>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.loaders.insert(mimetype.to_string(), Box::new(load));
+        self
+    }
+
+    /// The mimetypes which can be loaded, in alphabetical order.
+    pub fn mimetypes(&self) -> impl Iterator<Item = &str> + '_ {
+        self.loaders.keys().map(|m| m.as_str())
+    }
+
+    /// Load a model using the [`Backend`] registered for its mimetype.
+    ///
+    /// Aliases of the mimetypes `librunecoral` supports are resolved with
+    /// `resolve_mimetype()` first, and an empty
+    /// mimetype means the format is detected from the model's contents.
+    pub fn load(
+        &self,
+        mimetype: &str,
+        model: &[u8],
+        acceleration_backend: AccelerationBackend,
+    ) -> Result<Box<dyn Backend>, Error> {
+        let load = self
+            .loaders
+            .get(canonical_mimetype(mimetype, model))
+            .ok_or_else(|| LoadError::UnsupportedFormat {
+                mimetype: mimetype.to_string(),
+                supported: self.mimetypes().map(String::from).collect(),
+            })?;
+
+        load(model, acceleration_backend)
+    }
+}
+
+impl Default for BackendRegistry {
+    fn default() -> Self {
+        #[allow(unused_mut)]
+        let mut registry = BackendRegistry::new();
+
+        #[cfg(feature = "native")]
+        registry.register(crate::mimetype(), |model, acceleration_backend| {
+            let ctx = crate::InferenceContext::create_context(
+                crate::mimetype(),
+                model,
+                acceleration_backend,
+            )?;
+            Ok(Box::new(ctx))
+        });
+
+        registry
+    }
+}
+
+impl Debug for BackendRegistry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BackendRegistry")
+            .field("mimetypes", &self.mimetypes().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum LoadError {
+    /// No longer returned, because an unknown mimetype is reported as
+    /// [`LoadError::UnsupportedFormat`].
+    #[deprecated(note = "Unknown mimetypes are reported as LoadError::UnsupportedFormat")]
+    #[error("Incorrect mimetype")]
+    IncorrectMimeType,
+    #[error("Internal error")]
+    InternalError,
+    /// The model uses operators which can't be resolved. Use
+    /// [`Model::check_compatibility()`][crate::Model::check_compatibility]
+    /// to find out which ones.
+    #[error("The model uses unsupported operators")]
+    UnsupportedOperator,
+    /// The mimetype isn't supported, or no mimetype was given and the model's
+    /// format couldn't be detected.
+    #[error("Unsupported model format \"{mimetype}\" (supported: {})", .supported.join(", "))]
+    UnsupportedFormat {
+        mimetype: String,
+        supported: Vec<String>,
+    },
+    /// `librunecoral` was built from a different version of `runecoral.h`
+    /// than this crate, so it isn't safe to use.
+    #[error(
+        "librunecoral {library_version} isn't compatible with this crate (ABI version {actual_abi} instead of {expected_abi}, RuneCoralTensor is {actual_tensor_size} bytes instead of {expected_tensor_size})"
+    )]
+    IncompatibleLibrary {
+        library_version: String,
+        expected_abi: u32,
+        actual_abi: u32,
+        expected_tensor_size: usize,
+        actual_tensor_size: usize,
+    },
+    /// The model was compiled with `edgetpu_compiler`, so it can only be
+    /// loaded with the [`AccelerationBackend::EDGETPU`] backend on a machine
+    /// with an Edge TPU (see
+    /// [`Model::requires_edgetpu()`][crate::Model::requires_edgetpu]).
+    #[error("The model was compiled for the Edge TPU, but isn't being run on one")]
+    EdgeTpuRequired,
+    /// None of the connected Edge TPUs match the one requested with
+    /// [`ContextBuilder::edgetpu_device()`][crate::ContextBuilder::edgetpu_device].
+    #[error("Unable to find {selector} (found: {})", format_devices(.available))]
+    EdgeTpuNotFound {
+        selector: EdgeTpuSelector,
+        available: Vec<EdgeTpuDevice>,
+    },
+    /// The path of the selected Edge TPU is too long to pass to
+    /// `librunecoral`.
+    #[error("The Edge TPU path \"{path}\" is longer than {max_len} bytes")]
+    EdgeTpuPathTooLong { path: String, max_len: usize },
+    /// One of the [`EdgeTpuOptions`][crate::EdgeTpuOptions] is out of range.
+    #[error("Invalid Edge TPU option, {name} = {value}: {reason}")]
+    InvalidEdgeTpuOption {
+        name: String,
+        value: String,
+        reason: String,
+    },
+    /// `librunecoral` couldn't be loaded at runtime.
+    #[cfg(feature = "dynamic")]
+    #[error("librunecoral is unavailable")]
+    LibraryUnavailable(#[source] crate::library::LibraryError),
+    #[error("Unknown error {}", return_code)]
+    Other {
+        return_code: ffi::RuneCoralLoadResult,
+    },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, thiserror::Error)]
+pub enum InferError {
+    /// Generally referring to an error in the runtime (i.e. interpreter).
+    #[error("The TensorFlow Lite interpreter encountered an error")]
+    InterpreterError,
+    /// Generally referring to an error from a TfLiteDelegate itself.
+    #[error("A delegate returned an error")]
+    DelegateError,
+    // Generally referring to an error in applying a delegate due to
+    // incompatibility between runtime and delegate, e.g., this error is returned
+    // when trying to apply a TfLite delegate onto a model graph that's already
+    // immutable.
+    #[error("Invalid model graph or incompatibility between runtime and delegates")]
+    ApplicationError,
+    /// The input tensors don't have the element types or sizes the model
+    /// expects.
+    #[error("The inputs don't match the model: {}", _0)]
+    Inputs(TensorMismatch),
+    /// The output tensors don't have the element types or sizes the model
+    /// expects.
+    #[error("The outputs don't match the model: {}", _0)]
+    Outputs(TensorMismatch),
+    #[error("Unknown inference error {}", return_code)]
+    Other {
+        return_code: ffi::RuneCoralInferenceResult,
+    },
+}
+
+bitflags! {
+    pub struct AccelerationBackend: u32 {
+        const NONE = ffi::RuneCoralAccelerationBackend__None as u32;
+        const EDGETPU = ffi::RuneCoralAccelerationBackend__Edgetpu as u32;
+        const GPU = ffi::RuneCoralAccelerationBackend__Gpu as u32;
+        /// XNNPACK's optimized floating point kernels, which run on the CPU.
+        const XNNPACK = ffi::RuneCoralAccelerationBackend__Xnnpack as u32;
+    }
+}
+
+impl AccelerationBackend {
+    /// Get all [`AccelerationBackend`]s that are available on this device.
+    #[cfg(feature = "native")]
+    pub fn currently_available() -> Self {
+        // Nothing can be accelerated if librunecoral isn't installed
+        #[cfg(feature = "dynamic")]
+        if !crate::library::is_available() {
+            return AccelerationBackend::NONE;
+        }
+
+        unsafe {
+            AccelerationBackend::from_bits(ffi::availableAccelerationBackends() as u32).unwrap()
+        }
+    }
+
+    /// Get all [`AccelerationBackend`]s that are available on this device.
+    ///
+    /// Without the `native` feature there is nothing to accelerate, so this
+    /// is always [`AccelerationBackend::NONE`].
+    #[cfg(not(feature = "native"))]
+    pub fn currently_available() -> Self {
+        AccelerationBackend::NONE
+    }
+}
+
+/// The mimetype a [`BackendRegistry`] should look up, for a mimetype which
+/// may be an alias or empty.
+#[cfg(feature = "native")]
+fn canonical_mimetype<'a>(mimetype: &'a str, model: &[u8]) -> &'a str {
+    crate::resolve_mimetype(Some(mimetype), model).unwrap_or(mimetype)
+}
+
+/// The mimetype a [`BackendRegistry`] should look up, for a mimetype which
+/// may be an alias or empty.
+///
+/// Without the `native` feature there is no `librunecoral` to resolve
+/// aliases with, so mimetypes are used as-is.
+#[cfg(not(feature = "native"))]
+fn canonical_mimetype<'a>(mimetype: &'a str, _model: &[u8]) -> &'a str {
+    mimetype
+}
+
+fn format_devices(devices: &[EdgeTpuDevice]) -> String {
+    if devices.is_empty() {
+        return String::from("none");
+    }
+
+    let devices: Vec<_> = devices.iter().map(|d| d.to_string()).collect();
+    devices.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Identity;
+
+    impl Backend for Identity {
+        fn inputs(&self) -> Vec<TensorDescriptor<'_>> {
+            Vec::new()
+        }
+
+        fn outputs(&self) -> Vec<TensorDescriptor<'_>> {
+            Vec::new()
+        }
+
+        fn infer(&mut self, _: &[Tensor<'_>], _: &mut [TensorMut<'_>]) -> Result<(), InferError> {
+            Ok(())
+        }
+
+        fn capabilities(&self) -> Capabilities {
+            Capabilities {
+                acceleration_backend: AccelerationBackend::GPU,
+                ..Default::default()
+            }
+        }
+    }
+
+    #[test]
+    fn select_a_backend_by_mimetype() {
+        let mut registry = BackendRegistry::new();
+        registry.register("application/x-identity", |_, _| Ok(Box::new(Identity)));
+
+        let backend = registry
+            .load("application/x-identity", &[], AccelerationBackend::NONE)
+            .unwrap();
+
+        assert_eq!(
+            backend.capabilities().acceleration_backend,
+            AccelerationBackend::GPU
+        );
+        assert_eq!(
+            registry.mimetypes().collect::<Vec<_>>(),
+            ["application/x-identity"]
+        );
+    }
+
+    #[test]
+    fn unknown_mimetypes_are_rejected() {
+        let registry = BackendRegistry::new();
+
+        let err = registry
+            .load("application/x-unknown", &[], AccelerationBackend::NONE)
+            .unwrap_err();
+
+        assert_eq!(
+            err,
+            Error::Load(LoadError::UnsupportedFormat {
+                mimetype: "application/x-unknown".to_string(),
+                supported: Vec::new(),
+            })
+        );
+    }
+}
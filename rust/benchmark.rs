@@ -0,0 +1,356 @@
+use crate::{
+    mimetype, validate_inputs, AccelerationBackend, ElementType, Error, InferError,
+    InferenceContext, Tensor, TensorDescriptor, TensorMismatch, TensorMut,
+};
+use serde_json::json;
+use std::{
+    borrow::Cow,
+    time::{Duration, Instant},
+};
+
+/// Measure how long a model takes to load and run.
+///
+/// ```rust,no_run
+/// use hotg_runecoral::{AccelerationBackend, Benchmark};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let model = std::fs::read("model.tflite")?;
+///
+/// let report = Benchmark::new(&model)
+///     .acceleration_backend(AccelerationBackend::EDGETPU)
+///     .iterations(1000)
+///     .run()?;
+///
+/// println!("{}", report.to_json());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Benchmark<'a> {
+    model: &'a [u8],
+    acceleration_backend: AccelerationBackend,
+    warmup: usize,
+    iterations: usize,
+    inputs: Option<Vec<Tensor<'a>>>,
+    seed: u64,
+}
+
+impl<'a> Benchmark<'a> {
+    pub fn new(model: &'a [u8]) -> Self {
+        Benchmark {
+            model,
+            acceleration_backend: AccelerationBackend::NONE,
+            warmup: 5,
+            iterations: 100,
+            inputs: None,
+            seed: 0x5EED,
+        }
+    }
+
+    pub fn acceleration_backend(mut self, acceleration_backend: AccelerationBackend) -> Self {
+        self.acceleration_backend = acceleration_backend;
+        self
+    }
+
+    /// How many untimed runs to do before measuring (e.g. so caches and
+    /// delegates are warmed up).
+    pub fn warmup(mut self, warmup: usize) -> Self {
+        self.warmup = warmup;
+        self
+    }
+
+    /// How many timed runs to do.
+    pub fn iterations(mut self, iterations: usize) -> Self {
+        self.iterations = iterations;
+        self
+    }
+
+    /// Run the model on these inputs instead of randomly generated ones.
+    pub fn inputs(mut self, inputs: Vec<Tensor<'a>>) -> Self {
+        self.inputs = Some(inputs);
+        self
+    }
+
+    /// The seed used when generating random inputs.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn run(&self) -> Result<BenchmarkReport, BenchmarkError> {
+        let start = Instant::now();
+        let mut ctx = InferenceContext::builder()
+            .acceleration_backend(self.acceleration_backend)
+            .build(mimetype(), self.model)?;
+        let init_time = start.elapsed();
+
+        let random_inputs: Vec<(TensorDescriptor<'static>, Vec<u8>)>;
+        let inputs = match &self.inputs {
+            Some(inputs) => {
+                validate_inputs(inputs, ctx.inputs()).map_err(BenchmarkError::Inputs)?;
+                inputs.clone()
+            }
+            None => {
+                let mut rng = XorShift(self.seed.max(1));
+                random_inputs = ctx
+                    .inputs()
+                    .map(|d| (owned_descriptor(&d), random_buffer(&d, &mut rng)))
+                    .collect();
+                random_inputs
+                    .iter()
+                    .map(|(d, buffer)| Tensor {
+                        element_type: d.element_type,
+                        buffer,
+                        shape: d.shape.clone(),
+                    })
+                    .collect()
+            }
+        };
+
+        let output_descriptors: Vec<_> = ctx.outputs().map(|d| owned_descriptor(&d)).collect();
+        let mut output_buffers: Vec<_> = output_descriptors
+            .iter()
+            .map(|d| vec![0_u8; d.byte_len()])
+            .collect();
+        let mut outputs: Vec<_> = output_buffers
+            .iter_mut()
+            .zip(&output_descriptors)
+            .map(|(buffer, d)| TensorMut {
+                element_type: d.element_type,
+                buffer,
+                shape: d.shape.clone(),
+            })
+            .collect();
+
+        for _ in 0..self.warmup {
+            ctx.infer(&inputs, &mut outputs)?;
+        }
+
+        let mut samples = Vec::with_capacity(self.iterations);
+        for _ in 0..self.iterations {
+            let start = Instant::now();
+            ctx.infer(&inputs, &mut outputs)?;
+            samples.push(start.elapsed());
+        }
+
+        Ok(BenchmarkReport {
+            acceleration_backend: self.acceleration_backend,
+            init_time,
+            warmup: self.warmup,
+            latency: LatencyStats::from_samples(&samples),
+            peak_memory: peak_memory(),
+        })
+    }
+}
+
+/// The results from running a [`Benchmark`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchmarkReport {
+    pub acceleration_backend: AccelerationBackend,
+    /// How long it took to create the [`InferenceContext`].
+    pub init_time: Duration,
+    /// The number of untimed runs done before measuring.
+    pub warmup: usize,
+    pub latency: LatencyStats,
+    /// The process's peak resident memory usage in bytes, if it is known on
+    /// this platform.
+    pub peak_memory: Option<u64>,
+}
+
+impl BenchmarkReport {
+    /// Serialize the report as JSON, with all times in microseconds.
+    pub fn to_json(&self) -> String {
+        let latency = &self.latency;
+
+        json!({
+            "acceleration_backend": format!("{:?}", self.acceleration_backend),
+            "init_time_us": self.init_time.as_micros() as u64,
+            "warmup": self.warmup,
+            "iterations": latency.iterations,
+            "latency_us": {
+                "min": latency.min.as_micros() as u64,
+                "mean": latency.mean.as_micros() as u64,
+                "p50": latency.p50.as_micros() as u64,
+                "p90": latency.p90.as_micros() as u64,
+                "p99": latency.p99.as_micros() as u64,
+                "max": latency.max.as_micros() as u64,
+            },
+            "peak_memory_bytes": self.peak_memory,
+        })
+        .to_string()
+    }
+}
+
+/// Summary statistics for a set of inference timings.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct LatencyStats {
+    pub iterations: usize,
+    pub min: Duration,
+    pub mean: Duration,
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+impl LatencyStats {
+    pub fn from_samples(samples: &[Duration]) -> Self {
+        if samples.is_empty() {
+            return LatencyStats::default();
+        }
+
+        let mut sorted = samples.to_vec();
+        sorted.sort();
+        // Nearest-rank percentiles
+        let percentile = |p: usize| sorted[(p * sorted.len()).saturating_sub(1) / 100];
+        let total: Duration = sorted.iter().sum();
+
+        LatencyStats {
+            iterations: sorted.len(),
+            min: sorted[0],
+            mean: total / sorted.len() as u32,
+            p50: percentile(50),
+            p90: percentile(90),
+            p99: percentile(99),
+            max: sorted[sorted.len() - 1],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum BenchmarkError {
+    #[error("Unable to create the inference context")]
+    Context(#[from] Error),
+    #[error("Inference failed")]
+    Infer(#[from] InferError),
+    #[error("The inputs don't match the model: {}", _0)]
+    Inputs(TensorMismatch),
+}
+
+fn owned_descriptor(d: &TensorDescriptor<'_>) -> TensorDescriptor<'static> {
+    TensorDescriptor {
+        name: Default::default(),
+        element_type: d.element_type,
+        shape: Cow::Owned(d.shape.to_vec()),
+    }
+}
+
+/// Generate random elements, using values in `[0, 1)` for floats so they
+/// look like a normalized input.
+fn random_buffer(d: &TensorDescriptor<'_>, rng: &mut XorShift) -> Vec<u8> {
+    let len = d.byte_len();
+
+    match d.element_type {
+        ElementType::Float32 => (0..len / 4)
+            .flat_map(|_| (rng.next_f64() as f32).to_ne_bytes())
+            .collect(),
+        ElementType::Float64 => (0..len / 8)
+            .flat_map(|_| rng.next_f64().to_ne_bytes())
+            .collect(),
+        ElementType::Bool => (0..len).map(|_| (rng.next() & 1) as u8).collect(),
+        _ => (0..len).map(|_| rng.next() as u8).collect(),
+    }
+}
+
+/// A small, deterministic PRNG so benchmarks are repeatable without pulling
+/// in the `rand` crate.
+#[derive(Debug)]
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0 = x;
+        x
+    }
+
+    fn next_f64(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1_u64 << 53) as f64
+    }
+}
+
+/// The process's peak resident set size.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn peak_memory() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    let kilobytes: u64 = line
+        .trim_start_matches("VmHWM:")
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .ok()?;
+
+    Some(kilobytes * 1024)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn peak_memory() -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latency_percentiles() {
+        let samples: Vec<_> = (1..=100).rev().map(Duration::from_millis).collect();
+
+        let stats = LatencyStats::from_samples(&samples);
+
+        assert_eq!(stats.iterations, 100);
+        assert_eq!(stats.min, Duration::from_millis(1));
+        assert_eq!(stats.max, Duration::from_millis(100));
+        assert_eq!(stats.p50, Duration::from_millis(50));
+        assert_eq!(stats.p90, Duration::from_millis(90));
+        assert_eq!(stats.p99, Duration::from_millis(99));
+        assert_eq!(stats.mean, Duration::from_micros(50_500));
+    }
+
+    #[test]
+    fn no_samples_means_no_latency() {
+        assert_eq!(LatencyStats::from_samples(&[]), LatencyStats::default());
+    }
+
+    #[test]
+    fn random_inputs_are_repeatable_and_normalized() {
+        let descriptor = TensorDescriptor {
+            name: Default::default(),
+            element_type: ElementType::Float32,
+            shape: Cow::Borrowed(&[2, 8]),
+        };
+
+        let first = random_buffer(&descriptor, &mut XorShift(42));
+        let second = random_buffer(&descriptor, &mut XorShift(42));
+
+        assert_eq!(first, second);
+        assert_eq!(first.len(), 2 * 8 * 4);
+        for chunk in first.chunks_exact(4) {
+            let value = f32::from_ne_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+            assert!((0.0..1.0).contains(&value));
+        }
+    }
+
+    #[test]
+    fn report_as_json() {
+        let report = BenchmarkReport {
+            acceleration_backend: AccelerationBackend::NONE,
+            init_time: Duration::from_millis(3),
+            warmup: 1,
+            latency: LatencyStats::from_samples(&[Duration::from_micros(250)]),
+            peak_memory: None,
+        };
+
+        let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+
+        assert_eq!(json["init_time_us"], 3000);
+        assert_eq!(json["iterations"], 1);
+        assert_eq!(json["latency_us"]["p99"], 250);
+        assert!(json["peak_memory_bytes"].is_null());
+    }
+}
@@ -0,0 +1,509 @@
+//! A command-line tool for inspecting, running, and benchmarking TensorFlow
+//! Lite models with `librunecoral`.
+
+use anyhow::{Context, Error};
+use hotg_runecoral::{
+    edgetpu_devices, mimetype, testing::npy::NpyArray, validate_inputs, AccelerationBackend,
+    Benchmark, Comparison, ElementType, InferenceContext, Model, Target, TensorDescriptor,
+    TensorMut,
+};
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    convert::TryInto,
+    ffi::OsStr,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use structopt::StructOpt;
+
+fn main() -> Result<(), Error> {
+    match Args::from_args() {
+        Args::Inspect(inspect) => inspect.execute(),
+        Args::Run(run) => run.execute(),
+        Args::Backends => list_backends(),
+        Args::Bench(bench) => bench.execute(),
+        Args::Compare(compare) => compare.execute(),
+    }
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(about = "Inspect, run, and benchmark TensorFlow Lite models")]
+enum Args {
+    /// Print a model's inputs, outputs, operators, and metadata.
+    Inspect(Inspect),
+    /// Run a model on some inputs.
+    Run(Run),
+    /// List the acceleration backends available on this machine.
+    Backends,
+    /// Measure how long inference takes.
+    Bench(Bench),
+    /// Check that two backends or builds of a model give the same results.
+    Compare(Compare),
+}
+
+/// An [`AccelerationBackend`] that can be parsed from the command-line.
+#[derive(Debug, Copy, Clone)]
+struct Backend(AccelerationBackend);
+
+const BACKENDS: &[(&str, AccelerationBackend)] = &[
+    ("none", AccelerationBackend::NONE),
+    ("edgetpu", AccelerationBackend::EDGETPU),
+    ("gpu", AccelerationBackend::GPU),
+    ("xnnpack", AccelerationBackend::XNNPACK),
+];
+
+impl FromStr for Backend {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        BACKENDS
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(s))
+            .map(|&(_, backend)| Backend(backend))
+            .with_context(|| {
+                let names: Vec<_> = BACKENDS.iter().map(|(name, _)| *name).collect();
+                format!(
+                    "Unknown backend \"{}\", expected one of {}",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+#[derive(Debug, StructOpt)]
+struct Inspect {
+    /// The TensorFlow Lite model.
+    #[structopt(parse(from_os_str))]
+    model: PathBuf,
+}
+
+impl Inspect {
+    fn execute(self) -> Result<(), Error> {
+        let raw = read_model(&self.model)?;
+        let info = Model::verify(&raw).context("Invalid model")?;
+        let model = Model::load(&raw).context("Unable to load the model")?;
+
+        println!(
+            "{} ({} bytes, schema version {})",
+            self.model.display(),
+            info.size,
+            info.schema_version
+        );
+
+        if model.requires_edgetpu() {
+            println!("Compiled with edgetpu_compiler, so it needs an Edge TPU");
+        }
+
+        for (i, subgraph) in model.subgraphs().enumerate() {
+            println!();
+            println!("Subgraph {} \"{}\"", i, subgraph.name.to_string_lossy());
+
+            println!("  Inputs:");
+            for &index in subgraph.inputs {
+                print_graph_tensor(&subgraph, index);
+            }
+            println!("  Outputs:");
+            for &index in subgraph.outputs {
+                print_graph_tensor(&subgraph, index);
+            }
+
+            let mut op_counts = BTreeMap::new();
+            for op in &subgraph.operators {
+                let name = match op.custom_name {
+                    Some(custom) => custom.to_string_lossy(),
+                    None => op.name.to_string_lossy(),
+                };
+                *op_counts.entry(name).or_insert(0) += 1;
+            }
+            println!("  Operators:");
+            for (name, count) in op_counts {
+                println!("    {} x{}", name, count);
+            }
+        }
+
+        match model.metadata() {
+            Ok(Some(metadata)) => {
+                println!();
+                println!("Metadata:");
+                let fields = [
+                    ("Name", &metadata.name),
+                    ("Description", &metadata.description),
+                    ("Version", &metadata.version),
+                    ("Author", &metadata.author),
+                    ("License", &metadata.license),
+                ];
+                for (label, value) in &fields {
+                    if let Some(value) = value {
+                        println!("  {}: {}", label, value);
+                    }
+                }
+                for file in metadata.all_associated_files() {
+                    println!("  Associated file: {} ({:?})", file.name, file.kind);
+                }
+            }
+            Ok(None) => {}
+            Err(e) => println!("Unable to read the metadata: {}", e),
+        }
+
+        let ctx = load_context(&raw, Backend(AccelerationBackend::NONE))?;
+        let memory = ctx.memory_stats();
+        println!();
+        println!("Memory ({} bytes in total):", memory.total());
+        println!("  Model: {}", memory.model);
+        println!("  Arena: {}", memory.arena);
+        println!("  Persistent arena: {}", memory.persistent_arena);
+        println!("  Dynamic: {}", memory.dynamic);
+
+        Ok(())
+    }
+}
+
+fn print_graph_tensor(subgraph: &hotg_runecoral::Subgraph<'_>, index: i32) {
+    let tensor = match subgraph.tensor(index) {
+        Some(t) => t,
+        None => return,
+    };
+
+    match &tensor.quantization {
+        Some(q) => println!(
+            "    {} (scale {:?}, zero point {:?})",
+            tensor.descriptor(),
+            q.scale,
+            q.zero_point
+        ),
+        None => println!("    {}", tensor.descriptor()),
+    }
+}
+
+#[derive(Debug, StructOpt)]
+struct Run {
+    /// The TensorFlow Lite model.
+    #[structopt(parse(from_os_str))]
+    model: PathBuf,
+    /// A `.npy` or raw binary file for each of the model's inputs, in order.
+    #[structopt(short, long = "input", number_of_values = 1, parse(from_os_str))]
+    inputs: Vec<PathBuf>,
+    /// The hardware to run the model on.
+    #[structopt(short, long, default_value = "none")]
+    backend: Backend,
+    /// Save each output to `output_N.npy` in this directory.
+    #[structopt(short, long, parse(from_os_str))]
+    output_dir: Option<PathBuf>,
+}
+
+impl Run {
+    fn execute(self) -> Result<(), Error> {
+        let raw = read_model(&self.model)?;
+        let mut ctx = load_context(&raw, self.backend)?;
+        let inputs = read_inputs(&ctx, &self.inputs)?;
+
+        let outputs = infer(&mut ctx, &inputs)?;
+
+        if let Some(dir) = &self.output_dir {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Unable to create \"{}\"", dir.display()))?;
+        }
+
+        for (i, (output, descriptor)) in outputs.iter().zip(ctx.outputs()).enumerate() {
+            println!("{} = {}", descriptor, format_elements(output, 32));
+
+            if let Some(dir) = &self.output_dir {
+                let path = dir.join(format!("output_{}.npy", i));
+                std::fs::write(&path, output.to_bytes())
+                    .with_context(|| format!("Unable to write to \"{}\"", path.display()))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn list_backends() -> Result<(), Error> {
+    let available = AccelerationBackend::currently_available();
+    let version = hotg_runecoral::version().context("Unable to load librunecoral")?;
+
+    println!(
+        "librunecoral {} (TensorFlow Lite {})",
+        version.librunecoral, version.tflite
+    );
+
+    for &(name, backend) in BACKENDS {
+        if backend == AccelerationBackend::NONE {
+            continue;
+        }
+
+        let status = if available.contains(backend) {
+            "available"
+        } else {
+            "unavailable"
+        };
+        match version.delegates.iter().find(|(b, _)| *b == backend) {
+            Some((_, v)) => println!("{}: {} (version {})", name, status, v),
+            None => println!("{}: {}", name, status),
+        }
+
+        if backend == AccelerationBackend::EDGETPU {
+            for (i, device) in edgetpu_devices().iter().enumerate() {
+                println!("  #{}: {}", i, device);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, StructOpt)]
+struct Bench {
+    /// The TensorFlow Lite model.
+    #[structopt(parse(from_os_str))]
+    model: PathBuf,
+    /// A `.npy` or raw binary file for each of the model's inputs (defaults
+    /// to random values).
+    #[structopt(short, long = "input", number_of_values = 1, parse(from_os_str))]
+    inputs: Vec<PathBuf>,
+    /// The hardware to run the model on.
+    #[structopt(short, long, default_value = "none")]
+    backend: Backend,
+    /// How many times to run the model before timing starts.
+    #[structopt(long, default_value = "5")]
+    warmup: usize,
+    /// How many times to run the model.
+    #[structopt(short = "n", long, default_value = "100")]
+    iterations: usize,
+    /// The seed used when generating random inputs.
+    #[structopt(long, default_value = "24301")]
+    seed: u64,
+    /// Print the results as JSON.
+    #[structopt(long)]
+    json: bool,
+}
+
+impl Bench {
+    fn execute(self) -> Result<(), Error> {
+        let raw = read_model(&self.model)?;
+        let mut benchmark = Benchmark::new(&raw)
+            .acceleration_backend(self.backend.0)
+            .warmup(self.warmup)
+            .iterations(self.iterations)
+            .seed(self.seed);
+
+        let inputs = if self.inputs.is_empty() {
+            Vec::new()
+        } else {
+            let ctx = load_context(&raw, self.backend)?;
+            read_inputs(&ctx, &self.inputs)?
+        };
+        if !inputs.is_empty() {
+            benchmark = benchmark.inputs(inputs.iter().map(NpyArray::tensor).collect());
+        }
+
+        let report = benchmark.run()?;
+
+        if self.json {
+            println!("{}", report.to_json());
+            return Ok(());
+        }
+
+        let latency = &report.latency;
+        println!("Init: {:?}", report.init_time);
+        println!(
+            "{} iterations ({} warmup): min {:?}, mean {:?}, p50 {:?}, p90 {:?}, p99 {:?}, max {:?}",
+            latency.iterations,
+            report.warmup,
+            latency.min,
+            latency.mean,
+            latency.p50,
+            latency.p90,
+            latency.p99,
+            latency.max,
+        );
+        if let Some(peak) = report.peak_memory {
+            println!("Peak memory: {} KiB", peak / 1024);
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, StructOpt)]
+struct Compare {
+    /// The reference model.
+    #[structopt(parse(from_os_str))]
+    model: PathBuf,
+    /// A different build of the model (e.g. quantized) to compare against.
+    #[structopt(short, long, parse(from_os_str))]
+    candidate: Option<PathBuf>,
+    /// The backend to run the reference model on.
+    #[structopt(long, default_value = "none")]
+    reference_backend: Backend,
+    /// The backend to run the candidate model on.
+    #[structopt(long, default_value = "none")]
+    candidate_backend: Backend,
+    /// A `.npy` or raw binary file for each of the reference model's inputs
+    /// (defaults to zeroes).
+    #[structopt(short, long = "input", number_of_values = 1, parse(from_os_str))]
+    inputs: Vec<PathBuf>,
+    /// How many of the largest elements in each output to compare.
+    #[structopt(short = "k", long, default_value = "5")]
+    top_k: usize,
+}
+
+impl Compare {
+    fn execute(self) -> Result<(), Error> {
+        let reference = read_model(&self.model)?;
+        let candidate = match &self.candidate {
+            Some(path) => read_model(path)?,
+            None => reference.clone(),
+        };
+
+        let ctx = load_context(&reference, self.reference_backend)?;
+        let inputs = read_inputs(&ctx, &self.inputs)?;
+        drop(ctx);
+        let tensors: Vec<_> = inputs.iter().map(NpyArray::tensor).collect();
+
+        let report = Comparison::new(
+            Target::new(&reference, self.reference_backend.0),
+            Target::new(&candidate, self.candidate_backend.0),
+        )
+        .top_k(self.top_k)
+        .run(&tensors)?;
+
+        print!("{}", report);
+
+        Ok(())
+    }
+}
+
+fn read_model(path: &Path) -> Result<Vec<u8>, Error> {
+    std::fs::read(path).with_context(|| format!("Unable to read \"{}\"", path.display()))
+}
+
+fn load_context(model: &[u8], backend: Backend) -> Result<InferenceContext, Error> {
+    InferenceContext::builder()
+        .acceleration_backend(backend.0)
+        .build(mimetype(), model)
+        .context("Unable to create the inference context")
+}
+
+/// Read one file per input, using zeroes if no files were provided.
+fn read_inputs(ctx: &InferenceContext, paths: &[PathBuf]) -> Result<Vec<NpyArray>, Error> {
+    let descriptors: Vec<_> = ctx.inputs().collect();
+
+    if paths.is_empty() {
+        return Ok(descriptors.iter().map(zeroes).collect());
+    }
+
+    anyhow::ensure!(
+        paths.len() == descriptors.len(),
+        "The model has {} inputs, but {} were provided",
+        descriptors.len(),
+        paths.len()
+    );
+
+    let inputs = paths
+        .iter()
+        .zip(&descriptors)
+        .map(|(path, descriptor)| {
+            read_input(path, descriptor)
+                .with_context(|| format!("Unable to load \"{}\"", path.display()))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    let tensors: Vec<_> = inputs.iter().map(NpyArray::tensor).collect();
+    validate_inputs(&tensors, descriptors).context("The inputs don't match the model")?;
+
+    Ok(inputs)
+}
+
+fn read_input(path: &Path, descriptor: &TensorDescriptor<'_>) -> Result<NpyArray, Error> {
+    if path.extension() == Some(OsStr::new("npy")) {
+        let array = NpyArray::read(path)?;
+        anyhow::ensure!(
+            array.shape == descriptor.dimensions(),
+            "Expected the shape {:?} for {}, found {:?}",
+            descriptor.dimensions(),
+            descriptor,
+            array.shape
+        );
+        return Ok(array);
+    }
+
+    // Raw files are assumed to be in exactly the format the model expects
+    let data = std::fs::read(path)?;
+    Ok(NpyArray {
+        data,
+        ..zeroes(descriptor)
+    })
+}
+
+fn zeroes(descriptor: &TensorDescriptor<'_>) -> NpyArray {
+    NpyArray::new(
+        descriptor.element_type,
+        descriptor.dimensions(),
+        vec![0; descriptor.byte_len()],
+    )
+}
+
+fn infer(ctx: &mut InferenceContext, inputs: &[NpyArray]) -> Result<Vec<NpyArray>, Error> {
+    let mut outputs: Vec<_> = ctx.outputs().map(|d| zeroes(&d)).collect();
+    let inputs: Vec<_> = inputs.iter().map(NpyArray::tensor).collect();
+
+    let mut tensors: Vec<_> = outputs
+        .iter_mut()
+        .map(|output| TensorMut {
+            element_type: output.element_type,
+            buffer: &mut output.data,
+            shape: Cow::Owned(output.shape.iter().map(|&d| d as i32).collect()),
+        })
+        .collect();
+
+    ctx.infer(&inputs, &mut tensors)
+        .context("Inference failed")?;
+    drop(tensors);
+
+    Ok(outputs)
+}
+
+/// Format the first few elements of an array.
+fn format_elements(array: &NpyArray, max_elements: usize) -> String {
+    macro_rules! elements {
+        ($ty:ty) => {
+            array
+                .data
+                .chunks_exact(std::mem::size_of::<$ty>())
+                .take(max_elements)
+                .map(|chunk| <$ty>::from_ne_bytes(chunk.try_into().unwrap()).to_string())
+                .collect::<Vec<_>>()
+        };
+    }
+
+    let elements = match array.element_type {
+        ElementType::Bool => array
+            .data
+            .iter()
+            .take(max_elements)
+            .map(|&b| (b != 0).to_string())
+            .collect(),
+        ElementType::UInt8 => elements!(u8),
+        ElementType::Int8 => elements!(i8),
+        ElementType::Int16 => elements!(i16),
+        ElementType::Int32 => elements!(i32),
+        ElementType::Int64 => elements!(i64),
+        ElementType::Float32 => elements!(f32),
+        ElementType::Float64 => elements!(f64),
+        other => return format!("<{} bytes of {}>", array.data.len(), other),
+    };
+
+    let len = array.shape.iter().product::<usize>();
+    if len > elements.len() {
+        format!(
+            "[{}, ... ({} more)]",
+            elements.join(", "),
+            len - elements.len()
+        )
+    } else {
+        format!("[{}]", elements.join(", "))
+    }
+}
@@ -0,0 +1,774 @@
+//! Checking that two models or acceleration backends produce the same results.
+
+use crate::{
+    mimetype, model, tensors::decode_elements, validate_inputs, AccelerationBackend, ElementType,
+    Error, InferError, InferenceContext, LoadError, Model, Tensor, TensorDescriptor,
+    TensorMismatch, TensorMut,
+};
+use std::{
+    borrow::Cow,
+    cmp::Ordering,
+    fmt::{self, Display, Formatter},
+    os::raw::c_int,
+};
+
+/// A model and the hardware it should be run on.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Target<'a> {
+    pub model: &'a [u8],
+    pub acceleration_backend: AccelerationBackend,
+}
+
+impl<'a> Target<'a> {
+    pub fn new(model: &'a [u8], acceleration_backend: AccelerationBackend) -> Self {
+        Target {
+            model,
+            acceleration_backend,
+        }
+    }
+}
+
+/// Run the same inputs through a reference and a candidate [`Target`] and
+/// measure how much their outputs differ.
+///
+/// The two targets may be the same model on different backends (e.g.
+/// [`AccelerationBackend::NONE`] and [`AccelerationBackend::EDGETPU`]), or
+/// different builds of a model (e.g. float and quantized). Quantized inputs
+/// and outputs are converted to their real values, so a float model can be
+/// compared with its quantized version.
+///
+/// ```rust,no_run
+/// use hotg_runecoral::{AccelerationBackend, Comparison, Target, Tensor};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let model = std::fs::read("model.tflite")?;
+/// let input = [0.5_f32];
+///
+/// let report = Comparison::new(
+///     Target::new(&model, AccelerationBackend::NONE),
+///     Target::new(&model, AccelerationBackend::XNNPACK),
+/// )
+/// .run(&[Tensor::from_slice(&input, &[1, 1])])?;
+///
+/// println!("{}", report);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Comparison<'a> {
+    reference: Target<'a>,
+    candidate: Target<'a>,
+    top_k: usize,
+}
+
+impl<'a> Comparison<'a> {
+    pub fn new(reference: Target<'a>, candidate: Target<'a>) -> Self {
+        Comparison {
+            reference,
+            candidate,
+            top_k: 5,
+        }
+    }
+
+    /// How many of the largest elements in each output should be checked
+    /// for agreement (e.g. the top 5 classes for a classifier).
+    pub fn top_k(mut self, k: usize) -> Self {
+        self.top_k = k;
+        self
+    }
+
+    /// Run both targets on `inputs`, which should be in the format expected
+    /// by the reference target.
+    pub fn run(&self, inputs: &[Tensor<'_>]) -> Result<ComparisonReport, CompareError> {
+        let mut reference = Runner::load(self.reference, Side::Reference)?;
+        let mut candidate = Runner::load(self.candidate, Side::Candidate)?;
+
+        validate_inputs(inputs, reference.ctx.inputs()).map_err(CompareError::Inputs)?;
+        if reference.inputs.len() != candidate.inputs.len() {
+            return Err(CompareError::InputCountMismatch {
+                reference: reference.inputs.len(),
+                candidate: candidate.inputs.len(),
+            });
+        }
+        if reference.outputs.len() != candidate.outputs.len() {
+            return Err(CompareError::OutputCountMismatch {
+                reference: reference.outputs.len(),
+                candidate: candidate.outputs.len(),
+            });
+        }
+
+        for (index, (r, c)) in reference.inputs.iter().zip(&candidate.inputs).enumerate() {
+            if r.len != c.len {
+                return Err(CompareError::InputLengthMismatch {
+                    index,
+                    reference: r.len,
+                    candidate: c.len,
+                });
+            }
+        }
+
+        let candidate_inputs = inputs
+            .iter()
+            .zip(&reference.inputs)
+            .zip(&candidate.inputs)
+            .map(|((input, reference), candidate)| {
+                let real = reference.dequantize(input.element_type, input.buffer)?;
+                candidate.quantize(&real)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let reference_outputs = reference.infer(inputs.to_vec())?;
+        let candidate_inputs = candidate_inputs
+            .iter()
+            .zip(&candidate.inputs)
+            .map(|(buffer, info)| info.tensor(buffer))
+            .collect();
+        let candidate_outputs = candidate.infer(candidate_inputs)?;
+
+        let outputs = reference_outputs
+            .iter()
+            .zip(&candidate_outputs)
+            .zip(&reference.outputs)
+            .enumerate()
+            .map(|(index, ((r, c), info))| {
+                if r.len() != c.len() {
+                    return Err(CompareError::OutputLengthMismatch {
+                        index,
+                        reference: r.len(),
+                        candidate: c.len(),
+                    });
+                }
+
+                Ok(OutputComparison::new(
+                    index,
+                    info.name.clone(),
+                    r,
+                    c,
+                    self.top_k,
+                ))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(ComparisonReport { outputs })
+    }
+}
+
+/// Which of the [`Target`]s in a [`Comparison`] something refers to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Side {
+    Reference,
+    Candidate,
+}
+
+impl Display for Side {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Side::Reference => f.write_str("reference"),
+            Side::Candidate => f.write_str("candidate"),
+        }
+    }
+}
+
+/// How much the outputs from a [`Comparison`] differ.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComparisonReport {
+    pub outputs: Vec<OutputComparison>,
+}
+
+impl ComparisonReport {
+    /// The largest absolute error across all outputs.
+    pub fn max_abs_error(&self) -> f64 {
+        self.outputs
+            .iter()
+            .map(|o| o.max_abs_error)
+            .fold(0.0, f64::max)
+    }
+}
+
+impl Display for ComparisonReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for output in &self.outputs {
+            writeln!(f, "{}", output)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Statistics about how one output differs between the reference and
+/// candidate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputComparison {
+    pub index: usize,
+    pub name: String,
+    /// The number of elements in the output.
+    pub len: usize,
+    pub max_abs_error: f64,
+    pub mean_abs_error: f64,
+    /// The cosine similarity between the two outputs, where `1.0` means they
+    /// point in the same direction.
+    pub cosine_similarity: f64,
+    pub top_k: TopK,
+}
+
+impl OutputComparison {
+    fn new(index: usize, name: String, reference: &[f64], candidate: &[f64], k: usize) -> Self {
+        let errors: Vec<f64> = reference
+            .iter()
+            .zip(candidate)
+            .map(|(r, c)| (r - c).abs())
+            .collect();
+        let max_abs_error = errors.iter().copied().fold(0.0, f64::max);
+        let mean_abs_error = if errors.is_empty() {
+            0.0
+        } else {
+            errors.iter().sum::<f64>() / errors.len() as f64
+        };
+
+        OutputComparison {
+            index,
+            name,
+            len: reference.len(),
+            max_abs_error,
+            mean_abs_error,
+            cosine_similarity: cosine_similarity(reference, candidate),
+            top_k: TopK::new(reference, candidate, k),
+        }
+    }
+}
+
+impl Display for OutputComparison {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "output {} (\"{}\", {} elements): max abs error {:e}, mean abs error {:e}, cosine similarity {:.6}, {}",
+            self.index,
+            self.name,
+            self.len,
+            self.max_abs_error,
+            self.mean_abs_error,
+            self.cosine_similarity,
+            self.top_k,
+        )
+    }
+}
+
+/// How well the largest elements of two outputs agree.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TopK {
+    /// The number of elements compared, which may be less than requested if
+    /// the output is small.
+    pub k: usize,
+    /// How many of the reference's top `k` indices are also in the
+    /// candidate's top `k`.
+    pub overlap: usize,
+    /// Do both outputs have the same largest element?
+    pub top1_matches: bool,
+}
+
+impl TopK {
+    fn new(reference: &[f64], candidate: &[f64], k: usize) -> Self {
+        let k = k.min(reference.len());
+        let reference = top_indices(reference, k);
+        let candidate = top_indices(candidate, k);
+
+        TopK {
+            k,
+            overlap: reference.iter().filter(|i| candidate.contains(i)).count(),
+            top1_matches: reference.first() == candidate.first(),
+        }
+    }
+
+    /// The fraction of the top `k` elements both outputs agree on.
+    pub fn agreement(&self) -> f64 {
+        if self.k == 0 {
+            1.0
+        } else {
+            self.overlap as f64 / self.k as f64
+        }
+    }
+}
+
+impl Display for TopK {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "top-{} agreement {}/{} (top-1 {})",
+            self.k,
+            self.overlap,
+            self.k,
+            if self.top1_matches {
+                "matches"
+            } else {
+                "differs"
+            }
+        )
+    }
+}
+
+fn top_indices(values: &[f64], k: usize) -> Vec<usize> {
+    let mut indices: Vec<usize> = (0..values.len()).collect();
+    indices.sort_by(|&a, &b| values[b].partial_cmp(&values[a]).unwrap_or(Ordering::Equal));
+    indices.truncate(k);
+    indices
+}
+
+fn cosine_similarity(a: &[f64], b: &[f64]) -> f64 {
+    let dot: f64 = a.iter().zip(b).map(|(a, b)| a * b).sum();
+    let norm_a = a.iter().map(|a| a * a).sum::<f64>().sqrt();
+    let norm_b = b.iter().map(|b| b * b).sum::<f64>().sqrt();
+
+    match (norm_a == 0.0, norm_b == 0.0) {
+        (true, true) => 1.0,
+        (true, false) | (false, true) => 0.0,
+        (false, false) => dot / (norm_a * norm_b),
+    }
+}
+
+/// Errors from [`Comparison::run()`].
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum CompareError {
+    #[error("Unable to load the {side} model")]
+    Load {
+        side: Side,
+        #[source]
+        error: LoadError,
+    },
+    #[error("Unable to create the {side} inference context")]
+    Context {
+        side: Side,
+        #[source]
+        error: Error,
+    },
+    #[error("Inference failed for the {side} model")]
+    Infer {
+        side: Side,
+        #[source]
+        error: InferError,
+    },
+    #[error("The inputs don't match the reference model: {}", _0)]
+    Inputs(TensorMismatch),
+    #[error("The reference model has {reference} inputs, but the candidate has {candidate}")]
+    InputCountMismatch { reference: usize, candidate: usize },
+    #[error("Input {index} has {reference} elements in the reference model, but {candidate} in the candidate")]
+    InputLengthMismatch {
+        index: usize,
+        reference: usize,
+        candidate: usize,
+    },
+    #[error("The reference model has {reference} outputs, but the candidate has {candidate}")]
+    OutputCountMismatch { reference: usize, candidate: usize },
+    #[error("Output {index} has {reference} elements in the reference model, but {candidate} in the candidate")]
+    OutputLengthMismatch {
+        index: usize,
+        reference: usize,
+        candidate: usize,
+    },
+    #[error("Comparing {} tensors isn't supported", _0)]
+    UnsupportedElementType(ElementType),
+    #[error(
+        "The quantization parameters for \"{name}\" in the {side} model don't match its shape"
+    )]
+    UnsupportedQuantization { side: Side, name: String },
+}
+
+/// An [`InferenceContext`] and the information needed to convert its inputs
+/// and outputs to real values.
+struct Runner {
+    ctx: InferenceContext,
+    side: Side,
+    inputs: Vec<TensorInfo>,
+    outputs: Vec<TensorInfo>,
+}
+
+impl Runner {
+    fn load(target: Target<'_>, side: Side) -> Result<Self, CompareError> {
+        let model =
+            Model::load(target.model).map_err(|error| CompareError::Load { side, error })?;
+        let ctx = InferenceContext::builder()
+            .acceleration_backend(target.acceleration_backend)
+            .build(mimetype(), target.model)
+            .map_err(|error| CompareError::Context { side, error })?;
+
+        // Note: the context's inputs and outputs are in the same order as the
+        // primary subgraph's, but only the subgraph knows about quantization
+        let subgraph = model.subgraphs().next();
+        let info = |indices: Option<&[c_int]>, descriptors: Vec<TensorDescriptor<'_>>| {
+            descriptors
+                .into_iter()
+                .enumerate()
+                .map(|(i, d)| {
+                    let quantization = indices
+                        .and_then(|indices| indices.get(i))
+                        .and_then(|&index| subgraph.as_ref()?.tensor(index))
+                        .and_then(|t| t.quantization.as_ref());
+                    TensorInfo::new(&d, quantization, side)
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+
+        let inputs = info(subgraph.as_ref().map(|s| s.inputs), ctx.inputs().collect())?;
+        let outputs = info(
+            subgraph.as_ref().map(|s| s.outputs),
+            ctx.outputs().collect(),
+        )?;
+
+        Ok(Runner {
+            ctx,
+            side,
+            inputs,
+            outputs,
+        })
+    }
+
+    /// Run inference, returning the real value of each output.
+    fn infer(&mut self, inputs: Vec<Tensor<'_>>) -> Result<Vec<Vec<f64>>, CompareError> {
+        let mut buffers: Vec<Vec<u8>> = self
+            .outputs
+            .iter()
+            .map(|info| vec![0; info.byte_len])
+            .collect();
+        let mut outputs: Vec<_> = buffers
+            .iter_mut()
+            .zip(&self.outputs)
+            .map(|(buffer, info)| TensorMut {
+                element_type: info.element_type,
+                buffer,
+                shape: Cow::Borrowed(&info.shape),
+            })
+            .collect();
+
+        let side = self.side;
+        self.ctx
+            .infer(&inputs, &mut outputs)
+            .map_err(|error| CompareError::Infer { side, error })?;
+        drop(outputs);
+
+        buffers
+            .iter()
+            .zip(&self.outputs)
+            .map(|(buffer, info)| info.dequantize(info.element_type, buffer))
+            .collect()
+    }
+}
+
+struct TensorInfo {
+    name: String,
+    element_type: ElementType,
+    shape: Vec<c_int>,
+    /// The number of elements in the tensor.
+    len: usize,
+    byte_len: usize,
+    quantization: Option<Quantization>,
+}
+
+impl TensorInfo {
+    fn new(
+        descriptor: &TensorDescriptor<'_>,
+        quantization: Option<&model::Quantization<'_>>,
+        side: Side,
+    ) -> Result<Self, CompareError> {
+        let name = descriptor.name.to_string_lossy().into_owned();
+        let dimensions = descriptor.dimensions();
+        let quantization = match quantization {
+            Some(q) if !q.scale.is_empty() => match Quantization::new(q, &dimensions) {
+                Some(q) => Some(q),
+                None => return Err(CompareError::UnsupportedQuantization { side, name }),
+            },
+            _ => None,
+        };
+
+        Ok(TensorInfo {
+            name,
+            element_type: descriptor.element_type,
+            shape: descriptor.shape.to_vec(),
+            len: dimensions.iter().product(),
+            byte_len: descriptor.byte_len(),
+            quantization,
+        })
+    }
+
+    fn tensor<'a>(&self, buffer: &'a [u8]) -> Tensor<'a> {
+        Tensor {
+            element_type: self.element_type,
+            buffer,
+            shape: Cow::Owned(self.shape.clone()),
+        }
+    }
+
+    /// Convert a buffer of `element_type` elements to real values.
+    fn dequantize(
+        &self,
+        element_type: ElementType,
+        buffer: &[u8],
+    ) -> Result<Vec<f64>, CompareError> {
+        let values = decode(element_type, buffer)?;
+
+        match &self.quantization {
+            Some(q) if is_integer(element_type) => Ok(values
+                .into_iter()
+                .enumerate()
+                .map(|(i, value)| {
+                    let (scale, zero_point) = q.channel(i);
+                    scale * (value - zero_point)
+                })
+                .collect()),
+            _ => Ok(values),
+        }
+    }
+
+    /// Convert real values to a buffer this tensor can use.
+    fn quantize(&self, values: &[f64]) -> Result<Vec<u8>, CompareError> {
+        let values: Vec<f64> = match &self.quantization {
+            Some(q) if is_integer(self.element_type) => values
+                .iter()
+                .enumerate()
+                .map(|(i, value)| {
+                    let (scale, zero_point) = q.channel(i);
+                    (value / scale).round() + zero_point
+                })
+                .collect(),
+            _ => values.to_vec(),
+        };
+
+        encode(self.element_type, &values)
+    }
+}
+
+/// The scale and zero point used to convert a quantized tensor's elements
+/// to real values, either for the whole tensor or for each channel along
+/// one of its dimensions.
+#[derive(Debug, Clone, PartialEq)]
+struct Quantization {
+    scale: Vec<f64>,
+    zero_point: Vec<f64>,
+    /// The number of consecutive elements which belong to the same channel.
+    stride: usize,
+}
+
+impl Quantization {
+    /// Returns `None` if the parameters don't fit a tensor with these
+    /// dimensions.
+    fn new(q: &model::Quantization<'_>, dimensions: &[usize]) -> Option<Self> {
+        let channels = q.scale.len();
+        let zero_point = match q.zero_point.len() {
+            0 => vec![0.0; channels],
+            1 => vec![q.zero_point[0] as f64; channels],
+            n if n == channels => q.zero_point.iter().map(|&z| z as f64).collect(),
+            _ => return None,
+        };
+
+        if channels > 1 && dimensions.get(q.quantized_dimension) != Some(&channels) {
+            return None;
+        }
+
+        Some(Quantization {
+            scale: q.scale.iter().map(|&s| f64::from(s)).collect(),
+            zero_point,
+            stride: dimensions.iter().skip(q.quantized_dimension + 1).product(),
+        })
+    }
+
+    /// The `(scale, zero_point)` for the element at this index.
+    fn channel(&self, index: usize) -> (f64, f64) {
+        let channel = (index / self.stride.max(1)) % self.scale.len();
+        (self.scale[channel], self.zero_point[channel])
+    }
+}
+
+fn is_integer(element_type: ElementType) -> bool {
+    matches!(
+        element_type,
+        ElementType::UInt8
+            | ElementType::Int8
+            | ElementType::Int16
+            | ElementType::Int32
+            | ElementType::Int64
+    )
+}
+
+fn decode(element_type: ElementType, buffer: &[u8]) -> Result<Vec<f64>, CompareError> {
+    macro_rules! decode {
+        ($ty:ty) => {
+            decode_elements!(buffer, $ty, |v| v as f64)
+        };
+    }
+
+    let values = match element_type {
+        ElementType::Bool => buffer.iter().map(|&b| (b != 0) as u8 as f64).collect(),
+        ElementType::UInt8 => decode!(u8),
+        ElementType::Int8 => decode!(i8),
+        ElementType::Int16 => decode!(i16),
+        ElementType::Int32 => decode!(i32),
+        ElementType::Int64 => decode!(i64),
+        ElementType::Float32 => decode!(f32),
+        ElementType::Float64 => decode!(f64),
+        other => return Err(CompareError::UnsupportedElementType(other)),
+    };
+
+    Ok(values)
+}
+
+/// Encode real values as `element_type`, saturating at the type's bounds.
+fn encode(element_type: ElementType, values: &[f64]) -> Result<Vec<u8>, CompareError> {
+    macro_rules! encode {
+        ($ty:ty) => {
+            values
+                .iter()
+                .flat_map(|&v| (v as $ty).to_ne_bytes())
+                .collect()
+        };
+    }
+
+    let bytes = match element_type {
+        ElementType::Bool => values.iter().map(|&v| (v != 0.0) as u8).collect(),
+        ElementType::UInt8 => encode!(u8),
+        ElementType::Int8 => encode!(i8),
+        ElementType::Int16 => encode!(i16),
+        ElementType::Int32 => encode!(i32),
+        ElementType::Int64 => encode!(i64),
+        ElementType::Float32 => encode!(f32),
+        ElementType::Float64 => encode!(f64),
+        other => return Err(CompareError::UnsupportedElementType(other)),
+    };
+
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CStr;
+
+    #[test]
+    fn identical_outputs_have_no_error() {
+        let output = [0.1, 0.7, 0.2];
+
+        let comparison = OutputComparison::new(0, "out".to_string(), &output, &output, 2);
+
+        assert_eq!(comparison.max_abs_error, 0.0);
+        assert_eq!(comparison.mean_abs_error, 0.0);
+        assert!((comparison.cosine_similarity - 1.0).abs() < 1e-12);
+        assert_eq!(
+            comparison.top_k,
+            TopK {
+                k: 2,
+                overlap: 2,
+                top1_matches: true
+            }
+        );
+    }
+
+    #[test]
+    fn measure_the_difference_between_outputs() {
+        let reference = [0.1, 0.7, 0.2, 0.0];
+        let candidate = [0.1, 0.3, 0.6, 0.0];
+
+        let comparison = OutputComparison::new(0, "out".to_string(), &reference, &candidate, 3);
+
+        assert!((comparison.max_abs_error - 0.4).abs() < 1e-12);
+        assert!((comparison.mean_abs_error - 0.2).abs() < 1e-12);
+        assert!(comparison.cosine_similarity < 0.8);
+        // The same 3 classes are in the top 3, but in a different order
+        assert_eq!(comparison.top_k.overlap, 3);
+        assert!(!comparison.top_k.top1_matches);
+    }
+
+    #[test]
+    fn top_k_is_limited_by_the_output_size() {
+        let top_k = TopK::new(&[1.0], &[2.0], 5);
+
+        assert_eq!(top_k.k, 1);
+        assert_eq!(top_k.agreement(), 1.0);
+    }
+
+    #[test]
+    fn cosine_similarity_of_zero_vectors() {
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[0.0, 0.0]), 1.0);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 0.0]), 0.0);
+    }
+
+    #[test]
+    fn quantize_and_dequantize_real_values() {
+        let info = uint8_info(
+            vec![3],
+            model::Quantization {
+                scale: &[0.5],
+                zero_point: &[128],
+                quantized_dimension: 0,
+            },
+        );
+
+        let quantized = info.quantize(&[-1.0, 0.0, 1000.0]).unwrap();
+        assert_eq!(quantized, [126, 128, 255]);
+
+        let real = info.dequantize(ElementType::UInt8, &quantized).unwrap();
+        assert_eq!(real, [-1.0, 0.0, 63.5]);
+        // Float inputs are passed through as-is
+        let floats = f32_bytes(&[1.5]);
+        assert_eq!(
+            info.dequantize(ElementType::Float32, &floats).unwrap(),
+            [1.5]
+        );
+    }
+
+    #[test]
+    fn per_channel_quantization_uses_each_channels_parameters() {
+        // 2 channels along the last dimension
+        let info = uint8_info(
+            vec![2, 2],
+            model::Quantization {
+                scale: &[0.5, 2.0],
+                zero_point: &[0, 10],
+                quantized_dimension: 1,
+            },
+        );
+
+        let quantized = info.quantize(&[1.0, 2.0, 3.0, 4.0]).unwrap();
+        assert_eq!(quantized, [2, 11, 6, 12]);
+        assert_eq!(
+            info.dequantize(ElementType::UInt8, &quantized).unwrap(),
+            [1.0, 2.0, 3.0, 4.0]
+        );
+    }
+
+    #[test]
+    fn quantization_parameters_must_match_the_shape() {
+        let descriptor = TensorDescriptor {
+            name: CStr::from_bytes_with_nul(b"input\0").unwrap(),
+            element_type: ElementType::UInt8,
+            shape: Cow::Borrowed(&[1, 3]),
+        };
+        let quantization = model::Quantization {
+            scale: &[0.5, 2.0],
+            zero_point: &[0, 10],
+            quantized_dimension: 1,
+        };
+
+        let err = TensorInfo::new(&descriptor, Some(&quantization), Side::Candidate)
+            .err()
+            .unwrap();
+
+        assert_eq!(
+            err,
+            CompareError::UnsupportedQuantization {
+                side: Side::Candidate,
+                name: "input".to_string()
+            }
+        );
+    }
+
+    fn uint8_info(shape: Vec<c_int>, quantization: model::Quantization<'_>) -> TensorInfo {
+        let descriptor = TensorDescriptor {
+            name: CStr::from_bytes_with_nul(b"input\0").unwrap(),
+            element_type: ElementType::UInt8,
+            shape: Cow::Owned(shape),
+        };
+
+        TensorInfo::new(&descriptor, Some(&quantization), Side::Reference).unwrap()
+    }
+
+    fn f32_bytes(values: &[f32]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_ne_bytes()).collect()
+    }
+}
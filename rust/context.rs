@@ -1,16 +1,27 @@
-use crate::{ffi, Error, Tensor, TensorDescriptor, TensorMut};
-use bitflags::bitflags;
+use crate::{
+    edgetpu_devices, ffi, mimetypes::unsupported_format, ops::ResolverHandle, slice_from_raw,
+    tensors::check_tensors, version::check_abi, AccelerationBackend, Backend, Capabilities,
+    ContextState, EdgeTpuOptions, EdgeTpuSelector, ElementType, Error, InferError, LoadError,
+    OpResolver, Profile, RestoreError, Tensor, TensorDescriptor, TensorMut,
+};
 use std::{
+    borrow::Cow,
     convert::TryInto,
-    ffi::CString,
+    ffi::{CStr, CString, NulError},
     fmt::{self, Debug, Formatter},
     mem::MaybeUninit,
+    os::raw::c_int,
     ptr::NonNull,
+    sync::Arc,
 };
 
 /// A backend which can run inference on a model.
 pub struct InferenceContext {
     ctx: NonNull<ffi::RuneCoralContext>,
+    // Note: The interpreter refers to the resolver's custom ops, so it must
+    // outlive the context. Fields are dropped after Drop::drop() is called.
+    _op_resolver: Option<Arc<ResolverHandle>>,
+    acceleration_backend: AccelerationBackend,
 }
 
 impl InferenceContext {
@@ -20,8 +31,22 @@ impl InferenceContext {
     ///
     /// This takes ownership of the `ctx` pointer and will deallocate it on
     /// drop.
-    pub(crate) unsafe fn new(ctx: NonNull<ffi::RuneCoralContext>) -> Self {
-        InferenceContext { ctx }
+    pub(crate) unsafe fn new(
+        ctx: NonNull<ffi::RuneCoralContext>,
+        op_resolver: Option<Arc<ResolverHandle>>,
+        acceleration_backend: AccelerationBackend,
+    ) -> Self {
+        InferenceContext {
+            ctx,
+            _op_resolver: op_resolver,
+            acceleration_backend,
+        }
+    }
+
+    /// Get a [`ContextBuilder`] for creating an [`InferenceContext`] with
+    /// more control over the interpreter.
+    pub fn builder() -> ContextBuilder {
+        ContextBuilder::default()
     }
 
     pub fn infer(
@@ -29,6 +54,19 @@ impl InferenceContext {
         inputs: &[Tensor<'_>],
         outputs: &mut [TensorMut<'_>],
     ) -> Result<(), InferError> {
+        // librunecoral copies each tensor's full size in and out of our
+        // buffers, so they need to match exactly.
+        check_tensors(
+            self.raw_inputs().iter().map(expected_layout),
+            inputs.iter().map(|t| (t.element_type, t.buffer.len())),
+        )
+        .map_err(InferError::Inputs)?;
+        check_tensors(
+            self.raw_outputs().iter().map(expected_layout),
+            outputs.iter().map(|t| (t.element_type, t.buffer.len())),
+        )
+        .map_err(InferError::Outputs)?;
+
         // Safety: We are effectively casting a &T to a *mut T here. This is
         // okay, but only as long as the infer() function doesn't mutate the
         // input tensors in any way (casting from *mut T to &mut T would still
@@ -54,76 +92,454 @@ impl InferenceContext {
         model: &[u8],
         acceleration_backend: AccelerationBackend,
     ) -> Result<InferenceContext, Error> {
-        let mimetype = CString::new(mimetype)?;
+        InferenceContext::builder()
+            .acceleration_backend(acceleration_backend)
+            .build(mimetype, model)
+    }
+
+    pub fn opcount(&self) -> u64 {
+        unsafe { ffi::inference_opcount(self.ctx.as_ptr()).into() }
+    }
+
+    pub fn inputs(&self) -> impl Iterator<Item = TensorDescriptor<'_>> + '_ {
+        self.raw_inputs()
+            .iter()
+            .map(TensorDescriptor::from_rune_coral_tensor)
+    }
+
+    pub fn outputs(&self) -> impl Iterator<Item = TensorDescriptor<'_>> + '_ {
+        self.raw_outputs()
+            .iter()
+            .map(TensorDescriptor::from_rune_coral_tensor)
+    }
+
+    fn raw_inputs(&self) -> &[ffi::RuneCoralTensor] {
+        // Safety: The tensors live as long as the context does
+        unsafe {
+            let mut inputs = MaybeUninit::uninit();
+            let len = ffi::inference_inputs(self.ctx.as_ptr(), inputs.as_mut_ptr());
+
+            slice_from_raw(inputs.assume_init(), len as usize)
+        }
+    }
+
+    fn raw_outputs(&self) -> &[ffi::RuneCoralTensor] {
+        // Safety: The tensors live as long as the context does
+        unsafe {
+            let mut outputs = MaybeUninit::uninit();
+            let len = ffi::inference_outputs(self.ctx.as_ptr(), outputs.as_mut_ptr());
+
+            slice_from_raw(outputs.assume_init(), len as usize)
+        }
+    }
+
+    /// Start recording per-operator timings for every call to
+    /// [`InferenceContext::infer()`].
+    pub fn enable_profiling(&mut self) {
+        unsafe { ffi::inference_enable_profiling(self.ctx.as_ptr()) }
+    }
+
+    /// Stop recording per-operator timings.
+    pub fn disable_profiling(&mut self) {
+        unsafe { ffi::inference_disable_profiling(self.ctx.as_ptr()) }
+    }
+
+    /// The per-operator timings from the most recent call to
+    /// [`InferenceContext::infer()`].
+    ///
+    /// This will be empty unless profiling was enabled with
+    /// [`InferenceContext::enable_profiling()`].
+    pub fn profile(&self) -> Profile {
+        unsafe {
+            let mut events = MaybeUninit::uninit();
+            let len = ffi::inference_profile(self.ctx.as_ptr(), events.as_mut_ptr());
+            let events = events.assume_init();
+
+            if len == 0 {
+                return Profile::default();
+            }
+
+            Profile::from_events(std::slice::from_raw_parts(events, len as usize))
+        }
+    }
+
+    /// Reset every variable tensor (e.g. a recurrent model's state) to its
+    /// initial value.
+    pub fn reset_variable_tensors(&mut self) -> Result<(), InferError> {
+        let ret = unsafe { ffi::inference_reset_variable_tensors(self.ctx.as_ptr()) };
+        check_inference_error(ret)
+    }
+
+    /// Take a snapshot of the model's variable tensors.
+    pub fn save_state(&self) -> ContextState {
+        let tensors = self
+            .variable_tensors()
+            .iter()
+            .map(|tensor| unsafe {
+                slice_from_raw(tensor.data as *const u8, tensor.bytes as usize).to_vec()
+            })
+            .collect();
+
+        ContextState::new(tensors)
+    }
+
+    /// Overwrite the model's variable tensors with a snapshot from
+    /// [`InferenceContext::save_state()`].
+    ///
+    /// The snapshot may come from another [`InferenceContext`], as long as
+    /// it was created from the same model.
+    pub fn restore_state(&mut self, state: &ContextState) -> Result<(), RestoreError> {
+        let variables = self.variable_tensors();
+
+        if variables.len() != state.tensors().len() {
+            return Err(RestoreError::TensorCount {
+                expected: variables.len(),
+                actual: state.tensors().len(),
+            });
+        }
+
+        for (index, (tensor, saved)) in variables.iter().zip(state.tensors()).enumerate() {
+            if tensor.bytes as usize != saved.len() {
+                return Err(RestoreError::TensorSize {
+                    index,
+                    expected: tensor.bytes as usize,
+                    actual: saved.len(),
+                });
+            }
+        }
+
+        for (tensor, saved) in variables.iter().zip(state.tensors()) {
+            // TensorFlow Lite doesn't allocate any memory for zero-sized
+            // tensors, so their data pointer may be null.
+            if saved.is_empty() {
+                continue;
+            }
+            debug_assert!(!tensor.data.is_null());
+
+            // Safety: the tensor's data belongs to the interpreter, which we
+            // have exclusive access to, and we checked it is the same size.
+            unsafe {
+                std::ptr::copy_nonoverlapping(saved.as_ptr(), tensor.data.cast(), saved.len());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Every tensor used by the interpreter, including weights and
+    /// intermediate activations.
+    ///
+    /// Intermediate tensors only hold the values from the most recent call to
+    /// [`InferenceContext::infer()`] when the context was created with
+    /// [`ContextBuilder::preserve_all_tensors()`]. Otherwise their memory may
+    /// have been reused by later operators.
+    pub fn tensors(&self) -> Vec<InterpreterTensor<'_>> {
+        let tensors = unsafe {
+            let len = ffi::inference_tensors(self.ctx.as_ptr(), std::ptr::null_mut(), 0);
+            let mut tensors = vec![ffi::RuneCoralTensor::default(); len as usize];
+            let len = ffi::inference_tensors(
+                self.ctx.as_ptr(),
+                tensors.as_mut_ptr(),
+                tensors.len() as ffi::size_t,
+            );
+            tensors.truncate(len as usize);
+            tensors
+        };
+
+        tensors
+            .iter()
+            .enumerate()
+            .map(|(index, tensor)| {
+                // Safety: The name, shape, and data point into the
+                // interpreter, which can't be modified while we hold a
+                // reference to self.
+                unsafe {
+                    let shape = slice_from_raw(tensor.shape, tensor.rank as usize);
+
+                    InterpreterTensor {
+                        index,
+                        descriptor: TensorDescriptor {
+                            name: CStr::from_ptr(tensor.name),
+                            element_type: ElementType::from(tensor.type_),
+                            shape: Cow::Borrowed(shape),
+                        },
+                        data: if tensor.data.is_null() {
+                            None
+                        } else {
+                            Some(Tensor {
+                                element_type: ElementType::from(tensor.type_),
+                                buffer: slice_from_raw(
+                                    tensor.data as *const u8,
+                                    tensor.bytes as usize,
+                                ),
+                                shape: Cow::Borrowed(shape),
+                            })
+                        },
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Estimate how much memory this context is using.
+    pub fn memory_stats(&self) -> MemoryStats {
+        let mut stats = ffi::RuneCoralMemoryStats::default();
+
+        unsafe {
+            ffi::inference_memory_stats(self.ctx.as_ptr(), &mut stats);
+        }
+
+        MemoryStats {
+            model: stats.model as usize,
+            arena: stats.arena as usize,
+            persistent_arena: stats.persistent_arena as usize,
+            dynamic: stats.dynamic as usize,
+            delegate: stats.delegate as usize,
+        }
+    }
+
+    fn variable_tensors(&self) -> Vec<ffi::RuneCoralTensor> {
+        unsafe {
+            let len = ffi::inference_variable_tensors(self.ctx.as_ptr(), std::ptr::null_mut(), 0);
+            let mut tensors = vec![ffi::RuneCoralTensor::default(); len as usize];
+            let len = ffi::inference_variable_tensors(
+                self.ctx.as_ptr(),
+                tensors.as_mut_ptr(),
+                tensors.len() as ffi::size_t,
+            );
+            tensors.truncate(len as usize);
+
+            tensors
+        }
+    }
+}
+
+/// The element type and number of bytes the interpreter expects a tensor
+/// to have.
+fn expected_layout(tensor: &ffi::RuneCoralTensor) -> (ElementType, usize) {
+    (ElementType::from(tensor.type_), tensor.bytes as usize)
+}
+
+/// One of the tensors used by an [`InferenceContext`]'s interpreter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InterpreterTensor<'a> {
+    /// The tensor's index in the model's primary subgraph.
+    pub index: usize,
+    pub descriptor: TensorDescriptor<'a>,
+    /// The tensor's contents, if it has been allocated.
+    pub data: Option<Tensor<'a>>,
+}
+
+/// An estimate of the memory used by an [`InferenceContext`], in bytes.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct MemoryStats {
+    /// The context's copy of the model, including constant tensors like
+    /// weights.
+    pub model: usize,
+    /// The part of the tensor arena used for intermediate tensors, which is
+    /// reused as inference progresses.
+    pub arena: usize,
+    /// The part of the tensor arena used for tensors which live as long as
+    /// the context (e.g. variable tensors).
+    pub persistent_arena: usize,
+    /// Tensors which are allocated separately because their size is only
+    /// known at runtime.
+    pub dynamic: usize,
+    /// Tensor buffers owned by a delegate. Memory that delegates allocate
+    /// internally (e.g. on the accelerator) isn't included.
+    pub delegate: usize,
+}
+
+impl MemoryStats {
+    /// The total number of bytes used.
+    pub fn total(&self) -> usize {
+        self.model + self.arena + self.persistent_arena + self.dynamic + self.delegate
+    }
+}
+
+/// Options used when creating an [`InferenceContext`].
+pub struct ContextBuilder {
+    acceleration_backend: AccelerationBackend,
+    op_resolver: Option<Arc<ResolverHandle>>,
+    preserve_all_tensors: bool,
+    edgetpu_device: Option<EdgeTpuSelector>,
+    edgetpu_options: EdgeTpuOptions,
+}
+
+impl ContextBuilder {
+    /// Which hardware should be used to accelerate inference?
+    pub fn acceleration_backend(mut self, acceleration_backend: AccelerationBackend) -> Self {
+        self.acceleration_backend = acceleration_backend;
+        self
+    }
+
+    /// Use an [`OpResolver`] to find the implementations of the model's
+    /// operators (e.g. so it can use custom ops).
+    pub fn op_resolver(mut self, op_resolver: &OpResolver) -> Self {
+        self.op_resolver = Some(op_resolver.handle());
+        self
+    }
+
+    /// Keep the value of every intermediate tensor after inference so they
+    /// can be inspected with [`InferenceContext::tensors()`].
+    ///
+    /// This is intended for debugging because it stops the interpreter from
+    /// reusing memory, making inference use a lot more memory.
+    pub fn preserve_all_tensors(mut self, preserve_all_tensors: bool) -> Self {
+        self.preserve_all_tensors = preserve_all_tensors;
+        self
+    }
+
+    /// Run the model on a particular Edge TPU instead of the first one (see
+    /// [`edgetpu_devices()`][crate::edgetpu_devices]).
+    ///
+    /// This only matters when the [`AccelerationBackend::EDGETPU`] backend
+    /// is requested, in which case loading fails with
+    /// [`LoadError::EdgeTpuNotFound`] if no device matches.
+    pub fn edgetpu_device(mut self, selector: impl Into<EdgeTpuSelector>) -> Self {
+        self.edgetpu_device = Some(selector.into());
+        self
+    }
+
+    /// Settings for the Edge TPU delegate (e.g. to trade throughput for less
+    /// heat).
+    ///
+    /// The options are only used with the [`AccelerationBackend::EDGETPU`]
+    /// backend, but loading always fails with
+    /// [`LoadError::InvalidEdgeTpuOption`] if they are invalid.
+    pub fn edgetpu_options(mut self, options: EdgeTpuOptions) -> Self {
+        self.edgetpu_options = options;
+        self
+    }
+
+    /// Load a model, detecting its format from the contents when `mimetype`
+    /// is empty.
+    pub fn build(self, mimetype: &str, model: &[u8]) -> Result<InferenceContext, Error> {
+        #[cfg(feature = "dynamic")]
+        crate::library::check_available()?;
+        check_abi()?;
+
+        let c_mimetype = CString::new(mimetype)?;
         let mut inference_context = MaybeUninit::uninit();
 
+        let uses_edgetpu = self
+            .acceleration_backend
+            .contains(AccelerationBackend::EDGETPU);
+
+        let edgetpu_device = match &self.edgetpu_device {
+            Some(selector) if uses_edgetpu => Some(find_edgetpu(selector)?),
+            _ => None,
+        };
+
+        // Note: The options are always validated so mistakes are caught
+        // before the model is deployed to a machine with an Edge TPU
+        let edgetpu_options = self
+            .edgetpu_options
+            .to_pairs()?
+            .into_iter()
+            .filter(|_| uses_edgetpu)
+            .map(|(name, value)| Ok((CString::new(name)?, CString::new(value)?)))
+            .collect::<Result<Vec<_>, NulError>>()?;
+        let c_edgetpu_options: Vec<_> = edgetpu_options
+            .iter()
+            .map(|(name, value)| ffi::RuneCoralEdgetpuOption {
+                name: name.as_ptr(),
+                value: value.as_ptr(),
+            })
+            .collect();
+
+        let options = ffi::RuneCoralContextOptions {
+            backend: (self.acceleration_backend.bits() as i32)
+                .try_into()
+                .unwrap(),
+            resolver: self
+                .op_resolver
+                .as_ref()
+                .map_or(std::ptr::null(), |r| r.as_ptr()),
+            preserve_all_tensors: self.preserve_all_tensors as c_int,
+            edgetpu_device: edgetpu_device
+                .as_ref()
+                .map_or(std::ptr::null(), |device| device as *const _),
+            edgetpu_options: c_edgetpu_options.as_ptr(),
+            num_edgetpu_options: c_edgetpu_options.len() as ffi::size_t,
+        };
+
         // Safety: We've ensured our inputs are sane by construction (i.e. Rust
         // doesn't let you create a null slice and all enums are exhaustive)
-        // and our `inputs` and `outputs` tensor vector can't outlive the
-        // `inputs` and `outputs` function arguments.
+        // and the op resolver is kept alive by the InferenceContext.
         unsafe {
-            let ret = ffi::create_inference_context(
-                mimetype.as_ptr(),
+            let ret = ffi::create_inference_context_with_options(
+                c_mimetype.as_ptr(),
                 model.as_ptr().cast(),
                 model.len() as ffi::size_t,
-                (acceleration_backend.bits() as i32).try_into().unwrap(),
+                &options,
                 inference_context.as_mut_ptr(),
             );
 
+            if ret == ffi::RuneCoralLoadResult__UnsupportedFormat {
+                return Err(unsupported_format(mimetype).into());
+            }
             check_load_result(ret)?;
 
             let inference_context = inference_context.assume_init();
 
             Ok(InferenceContext::new(
                 NonNull::new(inference_context).expect("Should be initialized"),
+                self.op_resolver,
+                self.acceleration_backend,
             ))
         }
     }
+}
 
-    pub fn opcount(&self) -> u64 {
-        unsafe { ffi::inference_opcount(self.ctx.as_ptr()).into() }
+impl Default for ContextBuilder {
+    fn default() -> Self {
+        ContextBuilder {
+            acceleration_backend: AccelerationBackend::NONE,
+            op_resolver: None,
+            preserve_all_tensors: false,
+            edgetpu_device: None,
+            edgetpu_options: EdgeTpuOptions::default(),
+        }
     }
+}
 
-    pub fn inputs(&self) -> impl Iterator<Item = TensorDescriptor<'_>> + '_ {
-        unsafe {
-            let mut inputs = MaybeUninit::uninit();
-            let len = ffi::inference_inputs(self.ctx.as_ptr(), inputs.as_mut_ptr());
-
-            descriptors(inputs.assume_init(), len.into())
-        }
+impl Debug for ContextBuilder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ContextBuilder")
+            .field("acceleration_backend", &self.acceleration_backend)
+            .field("custom_op_resolver", &self.op_resolver.is_some())
+            .field("preserve_all_tensors", &self.preserve_all_tensors)
+            .field("edgetpu_device", &self.edgetpu_device)
+            .field("edgetpu_options", &self.edgetpu_options)
+            .finish()
     }
+}
 
-    pub fn outputs(&self) -> impl Iterator<Item = TensorDescriptor<'_>> + '_ {
-        unsafe {
-            let mut outputs = MaybeUninit::uninit();
-            let len = ffi::inference_outputs(self.ctx.as_ptr(), outputs.as_mut_ptr());
+impl Backend for InferenceContext {
+    fn inputs(&self) -> Vec<TensorDescriptor<'_>> {
+        InferenceContext::inputs(self).collect()
+    }
 
-            descriptors(outputs.assume_init(), len.into())
-        }
+    fn outputs(&self) -> Vec<TensorDescriptor<'_>> {
+        InferenceContext::outputs(self).collect()
     }
-}
 
-/// Iterate over the [`TensorDescriptor`]s for a set of tensors.
-///
-/// # Safety
-///
-/// The caller must ensure the returned iterator (`'a`) doesn't outlive the data
-/// pointed to by `tensors`.
-unsafe fn descriptors<'a>(
-    tensors: *const ffi::RuneCoralTensor,
-    len: u64,
-) -> impl Iterator<Item = TensorDescriptor<'a>> {
-    // Safety: Assumes the tensors are valid. The caller guarantees the 'a
-    // lifetime doesn't outlive the original tensors.
-    let tensors = if len > 0 {
-        std::slice::from_raw_parts(tensors, len as usize)
-    } else {
-        // Note: The tensors pointer may be null when len == 0, so let's swap it
-        // out with an empty slice
-        &[]
-    };
+    fn infer(
+        &mut self,
+        inputs: &[Tensor<'_>],
+        outputs: &mut [TensorMut<'_>],
+    ) -> Result<(), InferError> {
+        InferenceContext::infer(self, inputs, outputs)
+    }
 
-    tensors.iter().map(TensorDescriptor::from_rune_coral_tensor)
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            acceleration_backend: self.acceleration_backend,
+            profiling: true,
+            state: true,
+        }
+    }
 }
 
 impl Debug for InferenceContext {
@@ -140,23 +556,26 @@ impl Drop for InferenceContext {
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, thiserror::Error)]
-pub enum LoadError {
-    #[error("Incorrect mimetype")]
-    IncorrectMimeType,
-    #[error("Internal error")]
-    InternalError,
-    #[error("Unknown error {}", return_code)]
-    Other {
-        return_code: ffi::RuneCoralLoadResult,
-    },
+fn find_edgetpu(selector: &EdgeTpuSelector) -> Result<ffi::RuneCoralEdgetpuDevice, LoadError> {
+    let available = edgetpu_devices();
+
+    match selector.select(&available) {
+        Some(device) => device.to_ffi(),
+        None => Err(LoadError::EdgeTpuNotFound {
+            selector: selector.clone(),
+            available,
+        }),
+    }
 }
 
-fn check_load_result(return_code: ffi::RuneCoralLoadResult) -> Result<(), LoadError> {
+pub(crate) fn check_load_result(return_code: ffi::RuneCoralLoadResult) -> Result<(), LoadError> {
     match return_code {
         ffi::RuneCoralLoadResult__Ok => Ok(()),
+        #[allow(deprecated)]
         ffi::RuneCoralLoadResult__IncorrectMimeType => Err(LoadError::IncorrectMimeType),
         ffi::RuneCoralLoadResult__InternalError => Err(LoadError::InternalError),
+        ffi::RuneCoralLoadResult__UnsupportedOperator => Err(LoadError::UnsupportedOperator),
+        ffi::RuneCoralLoadResult__EdgetpuRequired => Err(LoadError::EdgeTpuRequired),
         _ => Err(LoadError::Other { return_code }),
     }
 }
@@ -178,43 +597,6 @@ fn check_inference_error(return_code: ffi::RuneCoralInferenceResult) -> Result<(
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, thiserror::Error)]
-pub enum InferError {
-    /// Generally referring to an error in the runtime (i.e. interpreter).
-    #[error("The TensorFlow Lite interpreter encountered an error")]
-    InterpreterError,
-    /// Generally referring to an error from a TfLiteDelegate itself.
-    #[error("A delegate returned an error")]
-    DelegateError,
-    // Generally referring to an error in applying a delegate due to
-    // incompatibility between runtime and delegate, e.g., this error is returned
-    // when trying to apply a TfLite delegate onto a model graph that's already
-    // immutable.
-    #[error("Invalid model graph or incompatibility between runtime and delegates")]
-    ApplicationError,
-    #[error("Unknown inference error {}", return_code)]
-    Other {
-        return_code: ffi::RuneCoralInferenceResult,
-    },
-}
-
-bitflags! {
-    pub struct AccelerationBackend: u32 {
-        const NONE = ffi::RuneCoralAccelerationBackend__None as u32;
-        const EDGETPU = ffi::RuneCoralAccelerationBackend__Edgetpu as u32;
-        const GPU = ffi::RuneCoralAccelerationBackend__Gpu as u32;
-    }
-}
-
-impl AccelerationBackend {
-    /// Get all [`AccelerationBackend`]s that are available on this device.
-    pub fn currently_available() -> Self {
-        unsafe {
-            AccelerationBackend::from_bits(ffi::availableAccelerationBackends() as u32).unwrap()
-        }
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use std::sync::Mutex;
@@ -0,0 +1,359 @@
+//! Finding, choosing between, and configuring the Edge TPUs connected to
+//! this machine.
+
+use crate::LoadError;
+use std::fmt::{self, Display, Formatter};
+
+#[cfg(feature = "native")]
+use crate::ffi;
+#[cfg(feature = "native")]
+use std::{ffi::CStr, os::raw::c_char};
+
+/// The custom operator `edgetpu_compiler` replaces the parts of a model that
+/// run on the Edge TPU with.
+pub const EDGETPU_CUSTOM_OP: &str = "edgetpu-custom-op";
+
+/// How an Edge TPU is connected.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum EdgeTpuDeviceType {
+    Pci,
+    Usb,
+}
+
+impl Display for EdgeTpuDeviceType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            EdgeTpuDeviceType::Pci => f.write_str("PCIe"),
+            EdgeTpuDeviceType::Usb => f.write_str("USB"),
+        }
+    }
+}
+
+/// An Edge TPU connected to this machine.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EdgeTpuDevice {
+    pub device_type: EdgeTpuDeviceType,
+    /// Where the device is (e.g. `/dev/apex_0` or `/sys/bus/usb/devices/2-1`).
+    pub path: String,
+}
+
+impl EdgeTpuDevice {
+    #[cfg(feature = "native")]
+    fn from_ffi(device: &ffi::RuneCoralEdgetpuDevice) -> Self {
+        let device_type = match device.type_ {
+            ffi::RuneCoralEdgetpuDeviceType__Usb => EdgeTpuDeviceType::Usb,
+            _ => EdgeTpuDeviceType::Pci,
+        };
+        // Safety: librunecoral always NUL-terminates the path
+        let path = unsafe { CStr::from_ptr(device.path.as_ptr()) };
+
+        EdgeTpuDevice {
+            device_type,
+            path: path.to_string_lossy().into_owned(),
+        }
+    }
+
+    /// Convert to the struct `librunecoral` uses, failing if the path doesn't
+    /// fit (silently truncating it could select a different device).
+    #[cfg(feature = "native")]
+    pub(crate) fn to_ffi(&self) -> Result<ffi::RuneCoralEdgetpuDevice, LoadError> {
+        let mut device = ffi::RuneCoralEdgetpuDevice {
+            type_: match self.device_type {
+                EdgeTpuDeviceType::Pci => ffi::RuneCoralEdgetpuDeviceType__Pci,
+                EdgeTpuDeviceType::Usb => ffi::RuneCoralEdgetpuDeviceType__Usb,
+            },
+            path: [0; 256],
+        };
+
+        // Leave room for the NUL terminator
+        let max_len = device.path.len() - 1;
+        if self.path.len() > max_len {
+            return Err(LoadError::EdgeTpuPathTooLong {
+                path: self.path.clone(),
+                max_len,
+            });
+        }
+
+        for (dest, &byte) in device.path.iter_mut().zip(self.path.as_bytes()) {
+            *dest = byte as c_char;
+        }
+
+        Ok(device)
+    }
+}
+
+impl Display for EdgeTpuDevice {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.path, self.device_type)
+    }
+}
+
+/// List the Edge TPUs connected to this machine.
+///
+/// This is empty when there aren't any, or `librunecoral` was built without
+/// the `edgetpu_acceleration` feature.
+#[cfg(feature = "native")]
+pub fn edgetpu_devices() -> Vec<EdgeTpuDevice> {
+    #[cfg(feature = "dynamic")]
+    if !crate::library::is_available() {
+        return Vec::new();
+    }
+
+    unsafe {
+        let len = ffi::runecoral_edgetpu_devices(std::ptr::null_mut(), 0);
+        let empty = ffi::RuneCoralEdgetpuDevice {
+            type_: ffi::RuneCoralEdgetpuDeviceType__Pci,
+            path: [0; 256],
+        };
+        let mut devices = vec![empty; len as usize];
+        let len =
+            ffi::runecoral_edgetpu_devices(devices.as_mut_ptr(), devices.len() as ffi::size_t);
+        devices.truncate(len as usize);
+
+        devices.iter().map(EdgeTpuDevice::from_ffi).collect()
+    }
+}
+
+/// Which Edge TPU should a model run on?
+///
+/// See [`ContextBuilder::edgetpu_device()`][crate::ContextBuilder::edgetpu_device].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum EdgeTpuSelector {
+    /// The device at this index in [`edgetpu_devices()`]'s list.
+    Index(usize),
+    /// The first device connected this way.
+    Type(EdgeTpuDeviceType),
+    /// The device with this path.
+    Path(String),
+}
+
+impl EdgeTpuSelector {
+    /// Find the device this selector refers to.
+    pub fn select<'a>(&self, devices: &'a [EdgeTpuDevice]) -> Option<&'a EdgeTpuDevice> {
+        match self {
+            EdgeTpuSelector::Index(index) => devices.get(*index),
+            EdgeTpuSelector::Type(device_type) => {
+                devices.iter().find(|d| d.device_type == *device_type)
+            }
+            EdgeTpuSelector::Path(path) => devices.iter().find(|d| d.path == *path),
+        }
+    }
+}
+
+impl Display for EdgeTpuSelector {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            EdgeTpuSelector::Index(index) => write!(f, "Edge TPU #{}", index),
+            EdgeTpuSelector::Type(device_type) => write!(f, "the first {} Edge TPU", device_type),
+            EdgeTpuSelector::Path(path) => write!(f, "the Edge TPU at \"{}\"", path),
+        }
+    }
+}
+
+impl From<usize> for EdgeTpuSelector {
+    fn from(index: usize) -> Self {
+        EdgeTpuSelector::Index(index)
+    }
+}
+
+impl From<EdgeTpuDeviceType> for EdgeTpuSelector {
+    fn from(device_type: EdgeTpuDeviceType) -> Self {
+        EdgeTpuSelector::Type(device_type)
+    }
+}
+
+impl From<&str> for EdgeTpuSelector {
+    fn from(path: &str) -> Self {
+        EdgeTpuSelector::Path(path.to_string())
+    }
+}
+
+/// How fast the Edge TPU runs. Faster means more throughput, but also more
+/// power and heat.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum EdgeTpuPerformance {
+    Low,
+    Medium,
+    High,
+    Max,
+}
+
+impl EdgeTpuPerformance {
+    fn as_str(self) -> &'static str {
+        match self {
+            EdgeTpuPerformance::Low => "Low",
+            EdgeTpuPerformance::Medium => "Medium",
+            EdgeTpuPerformance::High => "High",
+            EdgeTpuPerformance::Max => "Max",
+        }
+    }
+}
+
+/// Settings for libedgetpu's delegate.
+///
+/// Anything left as `None` uses libedgetpu's default.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct EdgeTpuOptions {
+    /// How fast to run the Edge TPU (libedgetpu defaults to
+    /// [`EdgeTpuPerformance::Max`]).
+    pub performance: Option<EdgeTpuPerformance>,
+    /// Always update a USB Edge TPU's firmware when opening it.
+    pub usb_always_dfu: Option<bool>,
+    /// How many USB bulk-in transfers can be queued at once, up to
+    /// [`EdgeTpuOptions::MAX_BULK_IN_QUEUE_LENGTH`] (libedgetpu defaults to 32).
+    pub usb_max_bulk_in_queue_length: Option<u32>,
+}
+
+impl EdgeTpuOptions {
+    /// The longest [`EdgeTpuOptions::usb_max_bulk_in_queue_length`]
+    /// libedgetpu accepts.
+    pub const MAX_BULK_IN_QUEUE_LENGTH: u32 = 255;
+
+    /// Check the options and convert them to the key/value pairs libedgetpu
+    /// expects (e.g. `("Performance", "Max")`).
+    pub fn to_pairs(&self) -> Result<Vec<(&'static str, String)>, LoadError> {
+        let mut pairs = Vec::new();
+
+        if let Some(performance) = self.performance {
+            pairs.push(("Performance", performance.as_str().to_string()));
+        }
+
+        if let Some(always_dfu) = self.usb_always_dfu {
+            let value = if always_dfu { "True" } else { "False" };
+            pairs.push(("Usb.AlwaysDfu", value.to_string()));
+        }
+
+        if let Some(length) = self.usb_max_bulk_in_queue_length {
+            if length > Self::MAX_BULK_IN_QUEUE_LENGTH {
+                return Err(LoadError::InvalidEdgeTpuOption {
+                    name: String::from("Usb.MaxBulkInQueueLength"),
+                    value: length.to_string(),
+                    reason: format!("must be at most {}", Self::MAX_BULK_IN_QUEUE_LENGTH),
+                });
+            }
+            pairs.push(("Usb.MaxBulkInQueueLength", length.to_string()));
+        }
+
+        Ok(pairs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn devices() -> Vec<EdgeTpuDevice> {
+        vec![
+            EdgeTpuDevice {
+                device_type: EdgeTpuDeviceType::Pci,
+                path: String::from("/dev/apex_0"),
+            },
+            EdgeTpuDevice {
+                device_type: EdgeTpuDeviceType::Usb,
+                path: String::from("/sys/bus/usb/devices/2-1"),
+            },
+            EdgeTpuDevice {
+                device_type: EdgeTpuDeviceType::Usb,
+                path: String::from("/sys/bus/usb/devices/2-2"),
+            },
+        ]
+    }
+
+    #[test]
+    fn select_devices() {
+        let devices = devices();
+        let inputs = vec![
+            (EdgeTpuSelector::Index(1), Some(&devices[1])),
+            (EdgeTpuSelector::Index(3), None),
+            (EdgeTpuDeviceType::Usb.into(), Some(&devices[1])),
+            (EdgeTpuDeviceType::Pci.into(), Some(&devices[0])),
+            ("/sys/bus/usb/devices/2-2".into(), Some(&devices[2])),
+            ("/dev/apex_1".into(), None),
+        ];
+
+        for (selector, should_be) in inputs {
+            assert_eq!(selector.select(&devices), should_be, "{}", selector);
+        }
+    }
+
+    #[test]
+    fn nothing_is_selected_without_devices() {
+        assert_eq!(EdgeTpuSelector::Index(0).select(&[]), None);
+    }
+
+    #[test]
+    #[cfg(feature = "native")]
+    fn round_trip_through_ffi() {
+        for device in devices() {
+            assert_eq!(EdgeTpuDevice::from_ffi(&device.to_ffi().unwrap()), device);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "native")]
+    fn overly_long_paths_are_rejected() {
+        let longest = EdgeTpuDevice {
+            device_type: EdgeTpuDeviceType::Usb,
+            path: "x".repeat(255),
+        };
+        let too_long = EdgeTpuDevice {
+            path: "x".repeat(256),
+            ..longest.clone()
+        };
+
+        let round_tripped = EdgeTpuDevice::from_ffi(&longest.to_ffi().unwrap());
+
+        assert_eq!(round_tripped, longest);
+        assert_eq!(
+            too_long.to_ffi().unwrap_err(),
+            LoadError::EdgeTpuPathTooLong {
+                path: too_long.path.clone(),
+                max_len: 255,
+            }
+        );
+    }
+
+    #[test]
+    fn default_options_use_the_libedgetpu_defaults() {
+        assert!(EdgeTpuOptions::default().to_pairs().unwrap().is_empty());
+    }
+
+    #[test]
+    fn convert_options_to_pairs() {
+        let options = EdgeTpuOptions {
+            performance: Some(EdgeTpuPerformance::Medium),
+            usb_always_dfu: Some(false),
+            usb_max_bulk_in_queue_length: Some(EdgeTpuOptions::MAX_BULK_IN_QUEUE_LENGTH),
+        };
+
+        let pairs = options.to_pairs().unwrap();
+
+        assert_eq!(
+            pairs,
+            vec![
+                ("Performance", String::from("Medium")),
+                ("Usb.AlwaysDfu", String::from("False")),
+                ("Usb.MaxBulkInQueueLength", String::from("255")),
+            ]
+        );
+    }
+
+    #[test]
+    fn the_bulk_in_queue_length_is_limited() {
+        let options = EdgeTpuOptions {
+            usb_max_bulk_in_queue_length: Some(EdgeTpuOptions::MAX_BULK_IN_QUEUE_LENGTH + 1),
+            ..Default::default()
+        };
+
+        let err = options.to_pairs().unwrap_err();
+
+        assert_eq!(
+            err,
+            LoadError::InvalidEdgeTpuOption {
+                name: String::from("Usb.MaxBulkInQueueLength"),
+                value: String::from("256"),
+                reason: String::from("must be at most 255"),
+            }
+        );
+    }
+}
@@ -2,5 +2,113 @@
 #![allow(dead_code, nonstandard_style)]
 // Bindgen generates funny tests - https://github.com/rust-lang/rust-bindgen/issues/1651
 #![allow(deref_nullptr)]
+// #defined strings become `&'static [u8; N]` constants
+#![allow(clippy::redundant_static_lifetimes)]
+// The struct bindgen generates for dynamic loading has undocumented methods
+#![cfg_attr(
+    feature = "dynamic",
+    allow(
+        missing_debug_implementations,
+        clippy::missing_safety_doc,
+        clippy::unused_unit
+    )
+)]
 
+#[cfg(feature = "native")]
 include!(concat!(env!("OUT_DIR"), "/", "bindings.rs"));
+
+// With the dynamic feature, bindgen generates a RuneCoral struct with a method
+// for each function instead of extern declarations
+#[cfg(feature = "dynamic")]
+pub use crate::library::functions::*;
+
+#[cfg(not(feature = "native"))]
+pub use self::portable::*;
+
+/// The subset of `runecoral.h` needed without `librunecoral` (e.g. for the
+/// mock backend), written by hand so we don't need to run bindgen.
+///
+/// These must be kept in sync with the header.
+#[cfg(any(not(feature = "native"), test))]
+#[cfg_attr(feature = "native", allow(unreachable_pub))]
+mod portable {
+    use std::os::raw::{c_char, c_int, c_uint, c_ulong, c_void};
+
+    pub type size_t = c_ulong;
+
+    pub type RuneCoralElementType = c_uint;
+    pub const RuneCoralElementType__NoType: RuneCoralElementType = 0;
+    pub const RuneCoralElementType__Float32: RuneCoralElementType = 1;
+    pub const RuneCoralElementType__Int32: RuneCoralElementType = 2;
+    pub const RuneCoralElementType__UInt8: RuneCoralElementType = 3;
+    pub const RuneCoralElementType__Int64: RuneCoralElementType = 4;
+    pub const RuneCoralElementType__String: RuneCoralElementType = 5;
+    pub const RuneCoralElementType__Bool: RuneCoralElementType = 6;
+    pub const RuneCoralElementType__Int16: RuneCoralElementType = 7;
+    pub const RuneCoralElementType__Complex64: RuneCoralElementType = 8;
+    pub const RuneCoralElementType__Int8: RuneCoralElementType = 9;
+    pub const RuneCoralElementType__Float16: RuneCoralElementType = 10;
+    pub const RuneCoralElementType__Float64: RuneCoralElementType = 11;
+    pub const RuneCoralElementType__Complex128: RuneCoralElementType = 12;
+
+    #[repr(C)]
+    #[derive(Debug, Copy, Clone)]
+    pub struct RuneCoralTensor {
+        pub type_: RuneCoralElementType,
+        pub name: *const c_char,
+        pub data: *mut c_void,
+        pub shape: *const c_int,
+        pub rank: size_t,
+        pub bytes: size_t,
+    }
+
+    pub type RuneCoralLoadResult = c_uint;
+    pub type RuneCoralInferenceResult = c_uint;
+
+    pub type RuneCoralAccelerationBackend = c_uint;
+    pub const RuneCoralAccelerationBackend__None: RuneCoralAccelerationBackend = 0;
+    pub const RuneCoralAccelerationBackend__Edgetpu: RuneCoralAccelerationBackend = 1;
+    pub const RuneCoralAccelerationBackend__Gpu: RuneCoralAccelerationBackend = 2;
+    pub const RuneCoralAccelerationBackend__Xnnpack: RuneCoralAccelerationBackend = 4;
+}
+
+#[cfg(all(test, feature = "native"))]
+mod tests {
+    use super::portable;
+
+    macro_rules! assert_same {
+        ($($name:ident),* $(,)?) => {
+            $(
+                assert_eq!(portable::$name, super::$name, stringify!($name));
+            )*
+        };
+    }
+
+    #[test]
+    fn portable_types_match_the_header() {
+        assert_eq!(
+            std::mem::size_of::<portable::RuneCoralTensor>(),
+            std::mem::size_of::<super::RuneCoralTensor>()
+        );
+
+        assert_same!(
+            RuneCoralElementType__NoType,
+            RuneCoralElementType__Float32,
+            RuneCoralElementType__Int32,
+            RuneCoralElementType__UInt8,
+            RuneCoralElementType__Int64,
+            RuneCoralElementType__String,
+            RuneCoralElementType__Bool,
+            RuneCoralElementType__Int16,
+            RuneCoralElementType__Complex64,
+            RuneCoralElementType__Int8,
+            RuneCoralElementType__Float16,
+            RuneCoralElementType__Float64,
+            RuneCoralElementType__Complex128,
+            RuneCoralAccelerationBackend__None,
+            RuneCoralAccelerationBackend__Edgetpu,
+            RuneCoralAccelerationBackend__Gpu,
+            RuneCoralAccelerationBackend__Xnnpack,
+        );
+    }
+}
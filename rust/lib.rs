@@ -4,6 +4,8 @@
 //!
 //! ```rust,no_run
 //! # fn load_model() -> &'static [u8] { todo!() }
+//! # #[cfg(not(feature = "native"))] fn main() {}
+//! # #[cfg(feature = "native")]
 //! # fn main() -> Result<(), Box<dyn std::error::Error>> {
 //! use hotg_runecoral::{Tensor, TensorMut, InferenceContext, AccelerationBackend};
 //!
@@ -20,7 +22,7 @@
 //!
 //! // load our inference backend
 //! let mut ctx = InferenceContext::create_context(
-//!     "application/tflite-context",
+//!     "application/tflite-model",
 //!     model,
 //!     AccelerationBackend::NONE,
 //! )?;
@@ -41,16 +43,80 @@
     unused_crate_dependencies
 )]
 
+mod backend;
+#[cfg(feature = "native")]
+mod benchmark;
+#[cfg(feature = "native")]
+mod compare;
+#[cfg(feature = "native")]
 mod context;
+mod edgetpu;
 pub mod ffi;
+#[cfg(feature = "dynamic")]
+pub mod library;
+mod metadata;
+#[cfg(feature = "mock")]
+pub mod mock;
+#[cfg(feature = "native")]
+mod mimetypes;
+#[cfg(feature = "native")]
+mod model;
+#[cfg(feature = "native")]
+mod ops;
+mod profiling;
+mod state;
 mod tensors;
+#[cfg(feature = "native")]
+mod version;
+#[cfg(feature = "testing")]
+pub mod testing;
 
 pub use crate::{
-    context::{AccelerationBackend, InferenceContext, LoadError},
-    tensors::{ElementType, Tensor, TensorDescriptor, TensorElement, TensorMut},
+    backend::{
+        AccelerationBackend, Backend, BackendRegistry, Capabilities, InferError, LoadError,
+    },
+    edgetpu::{
+        EdgeTpuDevice, EdgeTpuDeviceType, EdgeTpuOptions, EdgeTpuPerformance, EdgeTpuSelector,
+        EDGETPU_CUSTOM_OP,
+    },
+    metadata::{
+        AssociatedFile, AssociatedFileType, ColorSpace, ContentProperties, ImageProperties,
+        MetadataError, ModelMetadata, Normalization, Stats, SubgraphMetadata, TensorMetadata,
+        TFLITE_METADATA,
+    },
+    profiling::{OpEvent, Placement, Profile},
+    state::{ContextState, RestoreError},
+    tensors::{
+        validate_inputs, ElementType, Tensor, TensorDescriptor, TensorElement, TensorMismatch,
+        TensorMut, TypeMismatch,
+    },
 };
 
-use std::ffi::{CStr, NulError};
+#[cfg(feature = "native")]
+pub use crate::{
+    benchmark::{Benchmark, BenchmarkError, BenchmarkReport, LatencyStats},
+    compare::{
+        CompareError, Comparison, ComparisonReport, OutputComparison, Side, Target, TopK,
+    },
+    context::{ContextBuilder, InferenceContext, InterpreterTensor, MemoryStats},
+    edgetpu::edgetpu_devices,
+    mimetypes::{resolve_mimetype, supported_mimetypes},
+    model::{
+        GraphTensor, Model, ModelInfo, Operator, Quantization, Subgraph, UnsupportedOperator,
+        UnsupportedOperators, VerifyError,
+    },
+    ops::{CustomOp, CustomOpError, OpResolver},
+    version::{version, Version, ABI_VERSION},
+};
+
+use std::ffi::NulError;
+
+// These are only used by the runecoral binary
+#[cfg(feature = "cli")]
+use {anyhow as _, structopt as _};
+// Only used by the tests for InferenceContext and MockContext
+#[cfg(test)]
+use static_assertions as _;
 
 #[derive(Debug, Clone, PartialEq, thiserror::Error)]
 pub enum Error {
@@ -58,13 +124,37 @@ pub enum Error {
     InvalidString(#[from] NulError),
     #[error("Unable to load the model")]
     Load(#[from] LoadError),
+    #[error("Unable to register the \"{name}\" custom op")]
+    CustomOp { name: String },
 }
 
 /// The mimetype used by this crate to represent TensorFlow Lite models.
+#[cfg(all(feature = "native", not(feature = "dynamic")))]
 pub fn mimetype() -> &'static str {
     unsafe {
-        CStr::from_ptr(ffi::RUNE_CORAL_MIME_TYPE__TFLITE)
+        std::ffi::CStr::from_ptr(ffi::RUNE_CORAL_MIME_TYPE__TFLITE)
             .to_str()
             .unwrap()
     }
 }
+
+/// The mimetype used by this crate to represent TensorFlow Lite models.
+#[cfg(any(not(feature = "native"), feature = "dynamic"))]
+pub fn mimetype() -> &'static str {
+    "application/tflite-model"
+}
+
+/// Turn a pointer/length pair from `librunecoral` into a slice.
+///
+/// # Safety
+///
+/// The pointer must be valid for `len` elements and the caller must make sure
+/// `'a` doesn't outlive the data being pointed to.
+pub(crate) unsafe fn slice_from_raw<'a, T>(ptr: *const T, len: usize) -> &'a [T] {
+    // Note: The pointer may be null when len == 0
+    if len > 0 {
+        std::slice::from_raw_parts(ptr, len)
+    } else {
+        &[]
+    }
+}
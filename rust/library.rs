@@ -0,0 +1,274 @@
+//! Loading `librunecoral` at runtime instead of linking to it.
+//!
+//! With the `dynamic` feature enabled, the first call into `librunecoral`
+//! loads it from `$RUNECORAL_LIBRARY` or, if that isn't set, from the
+//! platform's default search path (e.g. `librunecoral.so` on Linux). Use
+//! [`load()`] to pick a library explicitly.
+//!
+//! ```rust,no_run
+//! use hotg_runecoral::library;
+//!
+//! if let Err(e) = library::load("/opt/runecoral/lib/librunecoral.so") {
+//!     eprintln!("Hardware acceleration is unavailable: {}", e);
+//! }
+//! ```
+
+use crate::ffi::RuneCoral;
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// The environment variable used to find `librunecoral` when [`load()`]
+/// hasn't been called.
+pub const LIBRARY_ENV_VAR: &str = "RUNECORAL_LIBRARY";
+
+// Note: The library is leaked once loaded because contexts and models may
+// still be using it, so it can never be unloaded.
+static LIBRARY: Mutex<Option<&'static RuneCoral>> = Mutex::new(None);
+
+/// Load `librunecoral` from a particular path.
+///
+/// This must be called before anything else uses the library.
+pub fn load(path: impl AsRef<Path>) -> Result<(), LibraryError> {
+    let mut library = LIBRARY.lock().unwrap();
+
+    if library.is_some() {
+        return Err(LibraryError::AlreadyLoaded);
+    }
+
+    *library = Some(open(path.as_ref())?);
+    Ok(())
+}
+
+/// Is `librunecoral` available, loading it from the default location if it
+/// hasn't been loaded yet?
+pub fn is_available() -> bool {
+    library().is_ok()
+}
+
+/// The path `librunecoral` will be loaded from by default.
+pub fn default_path() -> PathBuf {
+    match std::env::var_os(LIBRARY_ENV_VAR) {
+        Some(path) => PathBuf::from(path),
+        None => PathBuf::from(libloading::library_filename("runecoral")),
+    }
+}
+
+pub(crate) fn library() -> Result<&'static RuneCoral, LibraryError> {
+    let mut library = LIBRARY.lock().unwrap();
+
+    match *library {
+        Some(lib) => Ok(lib),
+        None => {
+            let lib = open(&default_path())?;
+            *library = Some(lib);
+            Ok(lib)
+        }
+    }
+}
+
+fn open(path: &Path) -> Result<&'static RuneCoral, LibraryError> {
+    // Safety: Loading a library runs its initializers, so we are trusting
+    // that whatever is at this path is actually librunecoral.
+    unsafe {
+        let library = libloading::Library::new(path).map_err(|e| LibraryError::Load {
+            path: path.to_path_buf(),
+            reason: e.to_string(),
+        })?;
+        let runecoral =
+            RuneCoral::from_library(library).map_err(|e| LibraryError::Incompatible {
+                path: path.to_path_buf(),
+                reason: e.to_string(),
+            })?;
+
+        Ok(Box::leak(Box::new(runecoral)))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum LibraryError {
+    #[error("Unable to load \"{}\": {reason}", .path.display())]
+    Load { path: PathBuf, reason: String },
+    /// The library is missing functions this crate needs, which usually
+    /// means it came from a different release of `librunecoral`.
+    #[error(
+        "\"{}\" isn't compatible with this version of hotg-runecoral: {reason}",
+        .path.display()
+    )]
+    Incompatible { path: PathBuf, reason: String },
+    #[error("librunecoral has already been loaded")]
+    AlreadyLoaded,
+}
+
+/// Make sure `librunecoral` can be used before trying to load a model.
+pub(crate) fn check_available() -> Result<(), crate::LoadError> {
+    library()
+        .map(|_| ())
+        .map_err(crate::LoadError::LibraryUnavailable)
+}
+
+macro_rules! forward_to_library {
+    ($(
+        fn $name:ident($($arg:ident: $ty:ty),* $(,)?) $(-> $ret:ty)?;
+    )*) => {
+        $(
+            /// Call the function with this name in the loaded `librunecoral`.
+            ///
+            /// # Safety
+            ///
+            /// The same as calling the function from `runecoral.h` directly.
+            ///
+            /// # Panics
+            ///
+            /// This panics if `librunecoral` couldn't be loaded.
+            pub unsafe fn $name($($arg: $ty),*) $(-> $ret)? {
+                match library() {
+                    Ok(lib) => lib.$name($($arg),*),
+                    Err(e) => panic!("{}", e),
+                }
+            }
+        )*
+    };
+}
+
+/// Free functions with the same signatures as a statically linked
+/// `librunecoral`, so the rest of the crate doesn't need to care how it was
+/// loaded.
+pub(crate) mod functions {
+    #![allow(non_snake_case)]
+
+    use super::library;
+    use crate::ffi::*;
+    use std::os::raw::{c_char, c_int, c_void};
+
+    forward_to_library! {
+        fn model_unresolved_ops(
+            model: *const RuneCoralModel,
+            resolver: *const RuneCoralOpResolver,
+            ops: *mut RuneCoralOpCode,
+            capacity: size_t,
+        ) -> size_t;
+        fn supported_mimetypes(mimetypes: *mut *const c_char, len: size_t) -> size_t;
+        fn resolve_mimetype(
+            mimetype: *const c_char,
+            model: *const c_void,
+            model_len: size_t,
+        ) -> *const c_char;
+        fn availableAccelerationBackends() -> c_int;
+        fn runecoral_version() -> *const c_char;
+        fn runecoral_abi_version() -> c_int;
+        fn runecoral_sizeof_tensor() -> size_t;
+        fn runecoral_tflite_version() -> *const c_char;
+        fn runecoral_delegate_version(backend: RuneCoralAccelerationBackend) -> *const c_char;
+        fn runecoral_edgetpu_devices(
+            devices: *mut RuneCoralEdgetpuDevice,
+            capacity: size_t,
+        ) -> size_t;
+        fn create_op_resolver() -> *mut RuneCoralOpResolver;
+        fn op_resolver_add_custom(
+            resolver: *mut RuneCoralOpResolver,
+            name: *const c_char,
+            version: c_int,
+            op: RuneCoralCustomOp,
+        ) -> c_int;
+        fn destroy_op_resolver(resolver: *mut RuneCoralOpResolver);
+        fn inference_tensors(
+            context: *const RuneCoralContext,
+            tensors: *mut RuneCoralTensor,
+            capacity: size_t,
+        ) -> size_t;
+        fn create_inference_context(
+            mimetype: *const c_char,
+            model: *const c_void,
+            model_len: size_t,
+            backend: RuneCoralAccelerationBackend,
+            inferenceContext: *mut *mut RuneCoralContext,
+        ) -> RuneCoralLoadResult;
+        fn create_inference_context_with_options(
+            mimetype: *const c_char,
+            model: *const c_void,
+            model_len: size_t,
+            options: *const RuneCoralContextOptions,
+            inferenceContext: *mut *mut RuneCoralContext,
+        ) -> RuneCoralLoadResult;
+        fn inference_opcount(inferenceContext: *const RuneCoralContext) -> size_t;
+        fn inference_inputs(
+            inferenceContext: *const RuneCoralContext,
+            tensors: *mut *const RuneCoralTensor,
+        ) -> size_t;
+        fn inference_outputs(
+            inferenceContext: *const RuneCoralContext,
+            tensors: *mut *const RuneCoralTensor,
+        ) -> size_t;
+        fn destroy_inference_context(inferenceContext: *mut RuneCoralContext);
+        fn infer(
+            context: *mut RuneCoralContext,
+            inputs: *const RuneCoralTensor,
+            num_inputs: size_t,
+            outputs: *mut RuneCoralTensor,
+            num_outputs: size_t,
+        ) -> RuneCoralInferenceResult;
+        fn inference_enable_profiling(context: *mut RuneCoralContext);
+        fn inference_disable_profiling(context: *mut RuneCoralContext);
+        fn inference_profile(
+            context: *const RuneCoralContext,
+            events: *mut *const RuneCoralProfileEvent,
+        ) -> size_t;
+        fn inference_reset_variable_tensors(
+            context: *mut RuneCoralContext,
+        ) -> RuneCoralInferenceResult;
+        fn inference_variable_tensors(
+            context: *const RuneCoralContext,
+            tensors: *mut RuneCoralTensor,
+            capacity: size_t,
+        ) -> size_t;
+        fn inference_memory_stats(
+            context: *const RuneCoralContext,
+            stats: *mut RuneCoralMemoryStats,
+        );
+        fn verify_model(
+            model: *const c_void,
+            model_len: size_t,
+            info: *mut RuneCoralModelInfo,
+        ) -> RuneCoralVerifyResult;
+        fn create_model(
+            model: *const c_void,
+            model_len: size_t,
+            modelOut: *mut *mut RuneCoralModel,
+        ) -> RuneCoralLoadResult;
+        fn model_subgraphs(
+            model: *const RuneCoralModel,
+            subgraphs: *mut *const RuneCoralSubgraph,
+        ) -> size_t;
+        fn model_metadata(
+            model: *const RuneCoralModel,
+            name: *const c_char,
+            data: *mut *const c_void,
+        ) -> size_t;
+        fn model_buffer(model: *const RuneCoralModel, data: *mut *const c_void) -> size_t;
+        fn destroy_model(model: *mut RuneCoralModel);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_libraries_are_reported() {
+        let path = Path::new("/path/to/nowhere/librunecoral.so");
+
+        let err = load(path).unwrap_err();
+
+        assert!(matches!(err, LibraryError::Load { path: p, .. } if p == path));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn libraries_without_the_runecoral_functions_are_incompatible() {
+        let err = load("libm.so.6").unwrap_err();
+
+        assert!(matches!(err, LibraryError::Incompatible { .. }));
+    }
+}
@@ -0,0 +1,727 @@
+//! Reading the [TensorFlow Lite Metadata][metadata] embedded in a model.
+//!
+//! [metadata]: https://www.tensorflow.org/lite/convert/metadata
+
+use std::convert::TryInto;
+#[cfg(feature = "native")]
+use std::io::{Cursor, Read};
+
+/// The name of the metadata buffer containing a `ModelMetadata` flatbuffer.
+pub const TFLITE_METADATA: &str = "TFLITE_METADATA";
+
+/// The file identifier used by the metadata flatbuffer schema.
+const METADATA_FILE_IDENTIFIER: &[u8] = b"M001";
+
+/// Errors that may occur while reading a model's metadata.
+#[derive(Debug, thiserror::Error)]
+pub enum MetadataError {
+    /// The metadata buffer isn't a valid `ModelMetadata` flatbuffer.
+    #[error("The model's metadata is malformed: {reason}")]
+    Malformed { reason: &'static str },
+    /// The model doesn't have an associated file with this name.
+    #[error("The model doesn't have an associated file called \"{name}\"")]
+    MissingAssociatedFile { name: String },
+    /// The associated files appended to the model couldn't be read.
+    #[error("Unable to read the model's associated files")]
+    Zip(#[from] zip::result::ZipError),
+    #[error("Unable to read the associated file")]
+    Io(#[from] std::io::Error),
+}
+
+/// Descriptive information about a model and how to use it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ModelMetadata {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub version: Option<String>,
+    pub author: Option<String>,
+    pub license: Option<String>,
+    /// The oldest version of the metadata parser which can read this
+    /// metadata.
+    pub min_parser_version: Option<String>,
+    /// Metadata for each subgraph in the model, in the same order as
+    /// [`crate::Model::subgraphs()`].
+    pub subgraphs: Vec<SubgraphMetadata>,
+    pub associated_files: Vec<AssociatedFile>,
+}
+
+impl ModelMetadata {
+    /// Parse a `TFLITE_METADATA` buffer.
+    pub fn parse(buffer: &[u8]) -> Result<Self, MetadataError> {
+        if buffer.get(4..8) != Some(METADATA_FILE_IDENTIFIER) {
+            return Err(MetadataError::Malformed {
+                reason: "missing the \"M001\" file identifier",
+            });
+        }
+
+        let root = Table::root(buffer)?;
+
+        Ok(ModelMetadata {
+            name: root.string(0)?,
+            description: root.string(1)?,
+            version: root.string(2)?,
+            subgraphs: root.tables(3, SubgraphMetadata::from_table)?,
+            author: root.string(4)?,
+            license: root.string(5)?,
+            associated_files: root.tables(6, AssociatedFile::from_table)?,
+            min_parser_version: root.string(7)?,
+        })
+    }
+
+    /// Every file associated with the model, its subgraphs, or their tensors.
+    pub fn all_associated_files(&self) -> impl Iterator<Item = &AssociatedFile> + '_ {
+        self.associated_files
+            .iter()
+            .chain(self.subgraphs.iter().flat_map(|s| {
+                s.associated_files
+                    .iter()
+                    .chain(s.inputs.iter().flat_map(|t| &t.associated_files))
+                    .chain(s.outputs.iter().flat_map(|t| &t.associated_files))
+            }))
+    }
+}
+
+/// Metadata for one of a model's subgraphs.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SubgraphMetadata {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub inputs: Vec<TensorMetadata>,
+    pub outputs: Vec<TensorMetadata>,
+    pub associated_files: Vec<AssociatedFile>,
+}
+
+impl SubgraphMetadata {
+    fn from_table(table: Table<'_>) -> Result<Self, MetadataError> {
+        Ok(SubgraphMetadata {
+            name: table.string(0)?,
+            description: table.string(1)?,
+            inputs: table.tables(2, TensorMetadata::from_table)?,
+            outputs: table.tables(3, TensorMetadata::from_table)?,
+            associated_files: table.tables(4, AssociatedFile::from_table)?,
+        })
+    }
+}
+
+/// Metadata describing one of a subgraph's input or output tensors.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TensorMetadata {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    /// Names for each of the tensor's dimensions (e.g. `["batch", "height",
+    /// "width", "channels"]`).
+    pub dimension_names: Vec<String>,
+    pub content: Option<ContentProperties>,
+    /// How input values should be normalized, or output values
+    /// denormalized.
+    pub normalization: Option<Normalization>,
+    pub stats: Option<Stats>,
+    pub associated_files: Vec<AssociatedFile>,
+}
+
+impl TensorMetadata {
+    fn from_table(table: Table<'_>) -> Result<Self, MetadataError> {
+        let mut normalization = None;
+
+        for unit in table.table_vector(4)? {
+            // NormalizationOptions is the first member of the
+            // ProcessUnitOptions union
+            if unit.scalar::<u8>(0)?.unwrap_or(0) == 1 {
+                if let Some(options) = unit.table(1)? {
+                    normalization = Some(Normalization {
+                        mean: options.floats(0)?,
+                        std: options.floats(1)?,
+                    });
+                }
+            }
+        }
+
+        Ok(TensorMetadata {
+            name: table.string(0)?,
+            description: table.string(1)?,
+            dimension_names: table.strings(2)?,
+            content: match table.table(3)? {
+                Some(content) => ContentProperties::from_table(content)?,
+                None => None,
+            },
+            normalization,
+            stats: match table.table(5)? {
+                Some(stats) => Some(Stats {
+                    max: stats.floats(0)?,
+                    min: stats.floats(1)?,
+                }),
+                None => None,
+            },
+            associated_files: table.tables(6, AssociatedFile::from_table)?,
+        })
+    }
+
+    /// The first file containing labels for this tensor's values (e.g. the
+    /// class names for a classifier's output).
+    pub fn label_file(&self) -> Option<&AssociatedFile> {
+        self.associated_files.iter().find(|f| {
+            matches!(
+                f.kind,
+                AssociatedFileType::TensorAxisLabels | AssociatedFileType::TensorValueLabels
+            )
+        })
+    }
+}
+
+/// What kind of data a tensor contains.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum ContentProperties {
+    Feature,
+    Image(ImageProperties),
+    BoundingBox,
+    Audio,
+    /// A type of content this crate doesn't know about.
+    Unknown(u8),
+}
+
+impl ContentProperties {
+    fn from_table(content: Table<'_>) -> Result<Option<Self>, MetadataError> {
+        let properties = match content.scalar::<u8>(0)?.unwrap_or(0) {
+            0 => return Ok(None),
+            1 => ContentProperties::Feature,
+            2 => {
+                let image = content.table(1)?;
+                let color_space = match image {
+                    Some(image) => image.scalar::<i8>(0)?.unwrap_or(0),
+                    None => 0,
+                };
+                let default_size = match image.map(|i| i.table(1)).transpose()?.flatten() {
+                    Some(size) => Some((
+                        size.scalar::<u32>(0)?.unwrap_or(0),
+                        size.scalar::<u32>(1)?.unwrap_or(0),
+                    )),
+                    None => None,
+                };
+
+                ContentProperties::Image(ImageProperties {
+                    color_space: ColorSpace::from_raw(color_space),
+                    default_size,
+                })
+            }
+            3 => ContentProperties::BoundingBox,
+            4 => ContentProperties::Audio,
+            other => ContentProperties::Unknown(other),
+        };
+
+        Ok(Some(properties))
+    }
+}
+
+/// Properties of an image tensor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageProperties {
+    pub color_space: ColorSpace,
+    /// The image's default `(width, height)`.
+    pub default_size: Option<(u32, u32)>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ColorSpace {
+    Unknown,
+    Rgb,
+    Grayscale,
+}
+
+impl ColorSpace {
+    fn from_raw(raw: i8) -> Self {
+        match raw {
+            1 => ColorSpace::Rgb,
+            2 => ColorSpace::Grayscale,
+            _ => ColorSpace::Unknown,
+        }
+    }
+}
+
+/// Normalization parameters, where `normalized = (value - mean) / std`.
+///
+/// There is either one value for the whole tensor or one per channel.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Normalization {
+    pub mean: Vec<f32>,
+    pub std: Vec<f32>,
+}
+
+/// The range of values a tensor can contain.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Stats {
+    pub max: Vec<f32>,
+    pub min: Vec<f32>,
+}
+
+/// A file which has been packed into the model (e.g. `labels.txt`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssociatedFile {
+    pub name: String,
+    pub description: Option<String>,
+    pub kind: AssociatedFileType,
+    pub locale: Option<String>,
+}
+
+impl AssociatedFile {
+    fn from_table(table: Table<'_>) -> Result<Self, MetadataError> {
+        Ok(AssociatedFile {
+            name: table.string(0)?.unwrap_or_default(),
+            description: table.string(1)?,
+            kind: AssociatedFileType::from_raw(table.scalar::<i8>(2)?.unwrap_or(0)),
+            locale: table.string(3)?,
+        })
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AssociatedFileType {
+    Unknown,
+    Descriptions,
+    /// One label per line, for each element along a tensor's axis.
+    TensorAxisLabels,
+    /// One label per line, for each value a tensor can contain.
+    TensorValueLabels,
+    TensorAxisScoreCalibration,
+    Vocabulary,
+    Other(i8),
+}
+
+impl AssociatedFileType {
+    fn from_raw(raw: i8) -> Self {
+        match raw {
+            0 => AssociatedFileType::Unknown,
+            1 => AssociatedFileType::Descriptions,
+            2 => AssociatedFileType::TensorAxisLabels,
+            3 => AssociatedFileType::TensorValueLabels,
+            4 => AssociatedFileType::TensorAxisScoreCalibration,
+            5 => AssociatedFileType::Vocabulary,
+            other => AssociatedFileType::Other(other),
+        }
+    }
+}
+
+/// Read a file from the zip archive appended to a model.
+#[cfg(feature = "native")]
+pub(crate) fn read_associated_file(model: &[u8], name: &str) -> Result<Vec<u8>, MetadataError> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(model))?;
+
+    let mut file = match archive.by_name(name) {
+        Ok(file) => file,
+        Err(zip::result::ZipError::FileNotFound) => {
+            return Err(MetadataError::MissingAssociatedFile {
+                name: name.to_string(),
+            })
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut contents = Vec::new();
+    file.read_to_end(&mut contents)?;
+
+    Ok(contents)
+}
+
+/// A bounds-checked view of a flatbuffer table.
+#[derive(Debug, Copy, Clone)]
+struct Table<'a> {
+    buffer: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Table<'a> {
+    fn root(buffer: &'a [u8]) -> Result<Self, MetadataError> {
+        let position = read_u32(buffer, 0)? as usize;
+        Table::at(buffer, position)
+    }
+
+    fn at(buffer: &'a [u8], position: usize) -> Result<Self, MetadataError> {
+        // Make sure the vtable is in bounds
+        let table = Table { buffer, position };
+        table.vtable()?;
+        Ok(table)
+    }
+
+    fn vtable(&self) -> Result<usize, MetadataError> {
+        let offset = read_u32(self.buffer, self.position)? as i32;
+        let vtable = self.position as i64 - offset as i64;
+
+        if vtable < 0 || vtable as usize + 4 > self.buffer.len() {
+            return Err(malformed("vtable out of range"));
+        }
+
+        Ok(vtable as usize)
+    }
+
+    /// Where a field's value is stored, or `None` if it isn't set.
+    fn field(&self, index: usize) -> Result<Option<usize>, MetadataError> {
+        let vtable = self.vtable()?;
+        let vtable_len = read_u16(self.buffer, vtable)? as usize;
+        let entry = 4 + 2 * index;
+
+        if entry + 2 > vtable_len {
+            return Ok(None);
+        }
+
+        match read_u16(self.buffer, vtable + entry)? {
+            0 => Ok(None),
+            offset => Ok(Some(self.position + offset as usize)),
+        }
+    }
+
+    /// Follow the offset stored in a field.
+    fn indirect(&self, index: usize) -> Result<Option<usize>, MetadataError> {
+        match self.field(index)? {
+            Some(position) => Ok(Some(position + read_u32(self.buffer, position)? as usize)),
+            None => Ok(None),
+        }
+    }
+
+    fn scalar<T: Scalar>(&self, index: usize) -> Result<Option<T>, MetadataError> {
+        match self.field(index)? {
+            Some(position) => T::read(self.buffer, position).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn string(&self, index: usize) -> Result<Option<String>, MetadataError> {
+        match self.indirect(index)? {
+            Some(position) => read_string(self.buffer, position).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn table(&self, index: usize) -> Result<Option<Table<'a>>, MetadataError> {
+        match self.indirect(index)? {
+            Some(position) => Table::at(self.buffer, position).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// The positions of each element in a vector field.
+    fn vector(&self, index: usize, element_size: usize) -> Result<Vec<usize>, MetadataError> {
+        let position = match self.indirect(index)? {
+            Some(position) => position,
+            None => return Ok(Vec::new()),
+        };
+
+        let len = read_u32(self.buffer, position)? as usize;
+        let start = position + 4;
+
+        match len.checked_mul(element_size).map(|size| start + size) {
+            Some(end) if end <= self.buffer.len() => {
+                Ok((0..len).map(|i| start + i * element_size).collect())
+            }
+            _ => Err(malformed("vector out of range")),
+        }
+    }
+
+    fn table_vector(&self, index: usize) -> Result<Vec<Table<'a>>, MetadataError> {
+        self.vector(index, 4)?
+            .into_iter()
+            .map(|position| {
+                let target = position + read_u32(self.buffer, position)? as usize;
+                Table::at(self.buffer, target)
+            })
+            .collect()
+    }
+
+    fn tables<T>(
+        &self,
+        index: usize,
+        parse: impl Fn(Table<'a>) -> Result<T, MetadataError>,
+    ) -> Result<Vec<T>, MetadataError> {
+        self.table_vector(index)?.into_iter().map(parse).collect()
+    }
+
+    fn strings(&self, index: usize) -> Result<Vec<String>, MetadataError> {
+        self.vector(index, 4)?
+            .into_iter()
+            .map(|position| {
+                read_string(
+                    self.buffer,
+                    position + read_u32(self.buffer, position)? as usize,
+                )
+            })
+            .collect()
+    }
+
+    fn floats(&self, index: usize) -> Result<Vec<f32>, MetadataError> {
+        self.vector(index, 4)?
+            .into_iter()
+            .map(|position| f32::read(self.buffer, position))
+            .collect()
+    }
+}
+
+trait Scalar: Sized {
+    fn read(buffer: &[u8], position: usize) -> Result<Self, MetadataError>;
+}
+
+macro_rules! impl_scalar {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl Scalar for $ty {
+                fn read(buffer: &[u8], position: usize) -> Result<Self, MetadataError> {
+                    const SIZE: usize = std::mem::size_of::<$ty>();
+                    let bytes = buffer
+                        .get(position..position + SIZE)
+                        .ok_or_else(|| malformed("field out of range"))?;
+                    Ok(<$ty>::from_le_bytes(bytes.try_into().unwrap()))
+                }
+            }
+        )*
+    };
+}
+
+impl_scalar!(u8, i8, u16, u32, f32);
+
+fn read_u16(buffer: &[u8], position: usize) -> Result<u16, MetadataError> {
+    u16::read(buffer, position)
+}
+
+fn read_u32(buffer: &[u8], position: usize) -> Result<u32, MetadataError> {
+    u32::read(buffer, position)
+}
+
+fn read_string(buffer: &[u8], position: usize) -> Result<String, MetadataError> {
+    let len = read_u32(buffer, position)? as usize;
+    let bytes = buffer
+        .get(position + 4..position + 4 + len)
+        .ok_or_else(|| malformed("string out of range"))?;
+
+    String::from_utf8(bytes.to_vec()).map_err(|_| malformed("strings must be valid UTF-8"))
+}
+
+fn malformed(reason: &'static str) -> MetadataError {
+    MetadataError::Malformed { reason }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A minimal flatbuffer builder, just for constructing test fixtures.
+    ///
+    /// Objects are appended front-to-back and every offset points forwards,
+    /// which flatbuffers allows even though `flatc` builds back-to-front.
+    #[derive(Default)]
+    struct Builder {
+        buffer: Vec<u8>,
+        fixups: Vec<(usize, usize)>,
+    }
+
+    enum Field<'a> {
+        Empty,
+        Byte(u8),
+        Offset(&'a dyn Fn(&mut Builder) -> usize),
+    }
+
+    impl Builder {
+        fn align(&mut self) {
+            while !self.buffer.len().is_multiple_of(4) {
+                self.buffer.push(0);
+            }
+        }
+
+        fn string(&mut self, s: &str) -> usize {
+            self.align();
+            let position = self.buffer.len();
+            self.buffer.extend((s.len() as u32).to_le_bytes());
+            self.buffer.extend(s.as_bytes());
+            self.buffer.push(0);
+            position
+        }
+
+        fn floats(&mut self, values: &[f32]) -> usize {
+            self.align();
+            let position = self.buffer.len();
+            self.buffer.extend((values.len() as u32).to_le_bytes());
+            for value in values {
+                self.buffer.extend(value.to_le_bytes());
+            }
+            position
+        }
+
+        fn offsets(&mut self, elements: &[&dyn Fn(&mut Builder) -> usize]) -> usize {
+            self.align();
+            let position = self.buffer.len();
+            self.buffer.extend((elements.len() as u32).to_le_bytes());
+            let slots = self.buffer.len();
+            self.buffer.extend(vec![0; 4 * elements.len()]);
+
+            for (i, element) in elements.iter().enumerate() {
+                let target = element(self);
+                self.fixups.push((slots + 4 * i, target));
+            }
+
+            position
+        }
+
+        /// Every field is stored in a 4-byte slot to keep things simple.
+        fn table(&mut self, fields: &[Field<'_>]) -> usize {
+            self.align();
+            let vtable = self.buffer.len();
+            self.buffer
+                .extend((4 + 2 * fields.len() as u16).to_le_bytes());
+            self.buffer
+                .extend((4 + 4 * fields.len() as u16).to_le_bytes());
+            for (i, field) in fields.iter().enumerate() {
+                let offset = match field {
+                    Field::Empty => 0,
+                    _ => 4 + 4 * i as u16,
+                };
+                self.buffer.extend(offset.to_le_bytes());
+            }
+
+            self.align();
+            let table = self.buffer.len();
+            self.buffer.extend(((table - vtable) as i32).to_le_bytes());
+            let slots = self.buffer.len();
+            self.buffer.extend(vec![0; 4 * fields.len()]);
+
+            for (i, field) in fields.iter().enumerate() {
+                let slot = slots + 4 * i;
+                match field {
+                    Field::Empty => {}
+                    Field::Byte(b) => self.buffer[slot] = *b,
+                    Field::Offset(f) => {
+                        let target = f(self);
+                        self.fixups.push((slot, target));
+                    }
+                }
+            }
+
+            table
+        }
+
+        fn finish(mut self, root: impl Fn(&mut Builder) -> usize) -> Vec<u8> {
+            self.buffer.extend([0; 4]);
+            self.buffer.extend(METADATA_FILE_IDENTIFIER);
+            let root = root(&mut self);
+            self.fixups.push((0, root));
+
+            for (slot, target) in self.fixups {
+                let offset = (target - slot) as u32;
+                self.buffer[slot..slot + 4].copy_from_slice(&offset.to_le_bytes());
+            }
+
+            self.buffer
+        }
+    }
+
+    fn labels_file(b: &mut Builder) -> usize {
+        b.table(&[
+            Field::Offset(&|b| b.string("labels.txt")),
+            Field::Empty,
+            Field::Byte(2),
+        ])
+    }
+
+    fn image_input(b: &mut Builder) -> usize {
+        let normalization = |b: &mut Builder| {
+            b.table(&[
+                Field::Offset(&|b| b.floats(&[127.5])),
+                Field::Offset(&|b| b.floats(&[127.5])),
+            ])
+        };
+        let process_unit =
+            move |b: &mut Builder| b.table(&[Field::Byte(1), Field::Offset(&normalization)]);
+        let image = |b: &mut Builder| b.table(&[Field::Byte(1)]);
+        let content = move |b: &mut Builder| b.table(&[Field::Byte(2), Field::Offset(&image)]);
+
+        b.table(&[
+            Field::Offset(&|b| b.string("image")),
+            Field::Empty,
+            Field::Empty,
+            Field::Offset(&content),
+            Field::Offset(&|b| b.offsets(&[&process_unit])),
+        ])
+    }
+
+    fn probability_output(b: &mut Builder) -> usize {
+        b.table(&[
+            Field::Offset(&|b| b.string("probability")),
+            Field::Empty,
+            Field::Empty,
+            Field::Empty,
+            Field::Empty,
+            Field::Empty,
+            Field::Offset(&|b| b.offsets(&[&labels_file])),
+        ])
+    }
+
+    fn classifier_metadata() -> Vec<u8> {
+        Builder::default().finish(|b| {
+            b.table(&[
+                Field::Offset(&|b| b.string("Image classifier")),
+                Field::Empty,
+                Field::Offset(&|b| b.string("v1")),
+                Field::Offset(&|b| {
+                    b.offsets(&[&|b| {
+                        b.table(&[
+                            Field::Empty,
+                            Field::Empty,
+                            Field::Offset(&|b| b.offsets(&[&image_input])),
+                            Field::Offset(&|b| b.offsets(&[&probability_output])),
+                        ])
+                    }])
+                }),
+            ])
+        })
+    }
+
+    #[test]
+    fn parse_classifier_metadata() {
+        let buffer = classifier_metadata();
+
+        let metadata = ModelMetadata::parse(&buffer).unwrap();
+
+        assert_eq!(metadata.name.as_deref(), Some("Image classifier"));
+        assert_eq!(metadata.version.as_deref(), Some("v1"));
+        assert_eq!(metadata.author, None);
+        assert_eq!(metadata.subgraphs.len(), 1);
+        let subgraph = &metadata.subgraphs[0];
+        let input = &subgraph.inputs[0];
+        assert_eq!(input.name.as_deref(), Some("image"));
+        assert_eq!(
+            input.normalization,
+            Some(Normalization {
+                mean: vec![127.5],
+                std: vec![127.5],
+            })
+        );
+        assert!(matches!(
+            input.content,
+            Some(ContentProperties::Image(ImageProperties {
+                color_space: ColorSpace::Rgb,
+                default_size: None,
+            }))
+        ));
+        let labels = subgraph.outputs[0].label_file().unwrap();
+        assert_eq!(labels.name, "labels.txt");
+        assert_eq!(labels.kind, AssociatedFileType::TensorAxisLabels);
+        assert_eq!(metadata.all_associated_files().count(), 1);
+    }
+
+    #[test]
+    fn reject_buffers_without_the_file_identifier() {
+        let mut buffer = classifier_metadata();
+        buffer[4..8].copy_from_slice(b"TFL3");
+
+        let err = ModelMetadata::parse(&buffer).unwrap_err();
+
+        assert!(matches!(err, MetadataError::Malformed { .. }));
+    }
+
+    #[test]
+    fn truncated_metadata_is_an_error_not_a_panic() {
+        let buffer = classifier_metadata();
+
+        for len in 0..buffer.len() {
+            let _ = ModelMetadata::parse(&buffer[..len]);
+        }
+    }
+}
@@ -0,0 +1,91 @@
+//! The model formats `librunecoral` knows how to load.
+
+use crate::{ffi, LoadError};
+use std::{
+    ffi::{CStr, CString},
+    os::raw::c_char,
+};
+
+/// The mimetypes of every model format `librunecoral` can load.
+///
+/// Aliases (e.g. `"application/x-tflite"`) are also accepted when loading a
+/// model, but aren't included here. Use [`resolve_mimetype()`] to find out
+/// which format an alias refers to.
+///
+/// This is empty when `librunecoral` can't be loaded.
+pub fn supported_mimetypes() -> Vec<&'static str> {
+    #[cfg(feature = "dynamic")]
+    if !crate::library::is_available() {
+        return Vec::new();
+    }
+
+    unsafe {
+        let len = ffi::supported_mimetypes(std::ptr::null_mut(), 0);
+        let mut mimetypes = vec![std::ptr::null(); len as usize];
+        let len = ffi::supported_mimetypes(mimetypes.as_mut_ptr(), mimetypes.len() as ffi::size_t);
+        mimetypes.truncate(len as usize);
+
+        mimetypes.into_iter().map(|m| static_str(m)).collect()
+    }
+}
+
+/// Get the canonical mimetype for a supported mimetype or one of its aliases,
+/// ignoring case.
+///
+/// When no mimetype is provided, the format is detected from the model's
+/// contents (e.g. the `TFL3` identifier used by TensorFlow Lite models).
+/// Nothing can be resolved when `librunecoral` can't be loaded.
+///
+/// ```rust,no_run
+/// use hotg_runecoral::{mimetype, resolve_mimetype};
+///
+/// assert_eq!(resolve_mimetype(Some("application/x-tflite"), &[]), Some(mimetype()));
+///
+/// let model = std::fs::read("model.tflite").unwrap();
+/// assert_eq!(resolve_mimetype(None, &model), Some(mimetype()));
+/// ```
+pub fn resolve_mimetype(mimetype: Option<&str>, model: &[u8]) -> Option<&'static str> {
+    #[cfg(feature = "dynamic")]
+    if !crate::library::is_available() {
+        return None;
+    }
+
+    let mimetype = match mimetype {
+        Some(m) => Some(CString::new(m).ok()?),
+        None => None,
+    };
+
+    unsafe {
+        let resolved = ffi::resolve_mimetype(
+            mimetype.as_ref().map_or(std::ptr::null(), |m| m.as_ptr()),
+            model.as_ptr().cast(),
+            model.len() as ffi::size_t,
+        );
+
+        if resolved.is_null() {
+            None
+        } else {
+            Some(static_str(resolved))
+        }
+    }
+}
+
+/// The error returned when `librunecoral` can't load a model with this
+/// mimetype.
+pub(crate) fn unsupported_format(mimetype: &str) -> LoadError {
+    LoadError::UnsupportedFormat {
+        mimetype: mimetype.to_string(),
+        supported: supported_mimetypes()
+            .into_iter()
+            .map(String::from)
+            .collect(),
+    }
+}
+
+/// # Safety
+///
+/// The pointer must be a valid C string which is never freed (e.g. a string
+/// literal).
+unsafe fn static_str(s: *const c_char) -> &'static str {
+    CStr::from_ptr(s).to_str().unwrap()
+}
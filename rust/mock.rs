@@ -0,0 +1,447 @@
+//! A pure-Rust stand-in for `InferenceContext`, so code which runs models can
+//! be unit tested without building `librunecoral`.
+//!
+//! ```rust
+//! use hotg_runecoral::{mock::MockContext, ElementType, Tensor, TensorMut};
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut ctx = MockContext::builder()
+//!     .input("x", ElementType::Float32, &[1, 1])
+//!     .output("y", ElementType::Float32, &[1, 1])
+//!     .on_infer(|inputs, outputs| {
+//!         let x = inputs[0].as_slice::<f32>().unwrap()[0];
+//!         outputs[0].as_mut_slice::<f32>().unwrap()[0] = x.sin();
+//!         Ok(())
+//!     })
+//!     .build()?;
+//!
+//! let input = [0.5_f32];
+//! let mut output = [0.0_f32];
+//! ctx.infer(
+//!     &[Tensor::from_slice(&input, &[1, 1])],
+//!     &mut [TensorMut::from_slice(&mut output, &[1, 1])],
+//! )?;
+//!
+//! assert_eq!(output[0], 0.5_f32.sin());
+//! assert_eq!(ctx.calls(), 1);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+    tensors::check_tensors, AccelerationBackend, Backend, Capabilities, ElementType, Error,
+    InferError, Tensor, TensorDescriptor, TensorMut,
+};
+use std::{
+    borrow::Cow,
+    ffi::CString,
+    fmt::{self, Debug, Formatter},
+    os::raw::c_int,
+};
+
+type InferFn = Box<dyn FnMut(&[Tensor<'_>], &mut [TensorMut<'_>]) -> Result<(), InferError> + Send>;
+
+/// A fake inference backend with scripted inputs and outputs.
+///
+/// Like the real `InferenceContext`, a [`MockContext`] is `Send` but not
+/// `Sync`.
+pub struct MockContext {
+    inputs: Vec<Descriptor>,
+    outputs: Vec<Descriptor>,
+    opcount: u64,
+    acceleration_backend: AccelerationBackend,
+    on_infer: Option<InferFn>,
+    calls: usize,
+}
+
+impl MockContext {
+    pub fn builder() -> MockContextBuilder {
+        MockContextBuilder::default()
+    }
+
+    /// Check the tensors match the scripted descriptors, then pass them to
+    /// the [`MockContextBuilder::on_infer()`] callback.
+    ///
+    /// The outputs are zeroed if no callback was provided.
+    pub fn infer(
+        &mut self,
+        inputs: &[Tensor<'_>],
+        outputs: &mut [TensorMut<'_>],
+    ) -> Result<(), InferError> {
+        check_tensors(
+            self.inputs.iter().map(Descriptor::layout),
+            inputs.iter().map(|t| (t.element_type, t.buffer.len())),
+        )
+        .map_err(InferError::Inputs)?;
+        check_tensors(
+            self.outputs.iter().map(Descriptor::layout),
+            outputs.iter().map(|t| (t.element_type, t.buffer.len())),
+        )
+        .map_err(InferError::Outputs)?;
+
+        match &mut self.on_infer {
+            Some(on_infer) => on_infer(inputs, outputs)?,
+            None => outputs.iter_mut().for_each(|t| t.buffer.fill(0)),
+        }
+
+        self.calls += 1;
+        Ok(())
+    }
+
+    pub fn opcount(&self) -> u64 {
+        self.opcount
+    }
+
+    pub fn inputs(&self) -> impl Iterator<Item = TensorDescriptor<'_>> + '_ {
+        self.inputs.iter().map(Descriptor::as_tensor_descriptor)
+    }
+
+    pub fn outputs(&self) -> impl Iterator<Item = TensorDescriptor<'_>> + '_ {
+        self.outputs.iter().map(Descriptor::as_tensor_descriptor)
+    }
+
+    /// The number of times [`MockContext::infer()`] has succeeded.
+    pub fn calls(&self) -> usize {
+        self.calls
+    }
+}
+
+impl Backend for MockContext {
+    fn inputs(&self) -> Vec<TensorDescriptor<'_>> {
+        MockContext::inputs(self).collect()
+    }
+
+    fn outputs(&self) -> Vec<TensorDescriptor<'_>> {
+        MockContext::outputs(self).collect()
+    }
+
+    fn infer(
+        &mut self,
+        inputs: &[Tensor<'_>],
+        outputs: &mut [TensorMut<'_>],
+    ) -> Result<(), InferError> {
+        MockContext::infer(self, inputs, outputs)
+    }
+
+    /// A [`MockContext`] can't be profiled and has no state to save.
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            acceleration_backend: self.acceleration_backend,
+            profiling: false,
+            state: false,
+        }
+    }
+}
+
+impl Debug for MockContext {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MockContext")
+            .field("inputs", &self.inputs)
+            .field("outputs", &self.outputs)
+            .field("opcount", &self.opcount)
+            .field("acceleration_backend", &self.acceleration_backend)
+            .field("calls", &self.calls)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Options used when creating a [`MockContext`].
+pub struct MockContextBuilder {
+    inputs: Vec<(String, ElementType, Vec<c_int>)>,
+    outputs: Vec<(String, ElementType, Vec<c_int>)>,
+    opcount: u64,
+    acceleration_backend: AccelerationBackend,
+    on_infer: Option<InferFn>,
+}
+
+impl Default for MockContextBuilder {
+    fn default() -> Self {
+        MockContextBuilder {
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            opcount: 0,
+            acceleration_backend: AccelerationBackend::NONE,
+            on_infer: None,
+        }
+    }
+}
+
+impl MockContextBuilder {
+    /// Add an input tensor to the model.
+    pub fn input(mut self, name: &str, element_type: ElementType, shape: &[usize]) -> Self {
+        self.inputs
+            .push((name.to_string(), element_type, to_shape(shape)));
+        self
+    }
+
+    /// Add an output tensor to the model.
+    pub fn output(mut self, name: &str, element_type: ElementType, shape: &[usize]) -> Self {
+        self.outputs
+            .push((name.to_string(), element_type, to_shape(shape)));
+        self
+    }
+
+    /// The value returned by [`MockContext::opcount()`].
+    pub fn opcount(mut self, opcount: u64) -> Self {
+        self.opcount = opcount;
+        self
+    }
+
+    /// The acceleration backend reported by [`Backend::capabilities()`].
+    pub fn acceleration_backend(mut self, acceleration_backend: AccelerationBackend) -> Self {
+        self.acceleration_backend = acceleration_backend;
+        self
+    }
+
+    /// Set the callback used to fill in the outputs whenever
+    /// [`MockContext::infer()`] is called.
+    pub fn on_infer<F>(mut self, on_infer: F) -> Self
+    where
+        F: FnMut(&[Tensor<'_>], &mut [TensorMut<'_>]) -> Result<(), InferError> + Send + 'static,
+    {
+        self.on_infer = Some(Box::new(on_infer));
+        self
+    }
+
+    pub fn build(self) -> Result<MockContext, Error> {
+        let MockContextBuilder {
+            inputs,
+            outputs,
+            opcount,
+            acceleration_backend,
+            on_infer,
+        } = self;
+
+        Ok(MockContext {
+            inputs: descriptors(inputs)?,
+            outputs: descriptors(outputs)?,
+            opcount,
+            acceleration_backend,
+            on_infer,
+            calls: 0,
+        })
+    }
+}
+
+impl Debug for MockContextBuilder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MockContextBuilder")
+            .field("inputs", &self.inputs)
+            .field("outputs", &self.outputs)
+            .field("opcount", &self.opcount)
+            .field("acceleration_backend", &self.acceleration_backend)
+            .field("on_infer", &self.on_infer.is_some())
+            .finish()
+    }
+}
+
+#[derive(Debug)]
+struct Descriptor {
+    name: CString,
+    element_type: ElementType,
+    shape: Vec<c_int>,
+}
+
+impl Descriptor {
+    fn as_tensor_descriptor(&self) -> TensorDescriptor<'_> {
+        TensorDescriptor {
+            name: &self.name,
+            element_type: self.element_type,
+            shape: Cow::Borrowed(&self.shape),
+        }
+    }
+
+    /// The element type and number of bytes a tensor should have.
+    fn layout(&self) -> (ElementType, usize) {
+        (self.element_type, self.as_tensor_descriptor().byte_len())
+    }
+}
+
+fn to_shape(shape: &[usize]) -> Vec<c_int> {
+    shape.iter().map(|&d| d as c_int).collect()
+}
+
+fn descriptors(tensors: Vec<(String, ElementType, Vec<c_int>)>) -> Result<Vec<Descriptor>, Error> {
+    tensors
+        .into_iter()
+        .map(|(name, element_type, shape)| {
+            Ok(Descriptor {
+                name: CString::new(name)?,
+                element_type,
+                shape,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::TensorMismatch;
+
+    fn sine() -> MockContextBuilder {
+        MockContext::builder()
+            .input("dense_2_input", ElementType::Float32, &[1, 1])
+            .output("Identity", ElementType::Float32, &[1, 1])
+    }
+
+    #[test]
+    fn mock_context_is_only_send() {
+        static_assertions::assert_impl_all!(MockContext: Send);
+        static_assertions::assert_not_impl_any!(MockContext: Sync);
+        static_assertions::assert_impl_all!(Mutex<MockContext>: Send, Sync);
+    }
+
+    #[test]
+    fn scripted_descriptors() {
+        let ctx = sine().opcount(3).build().unwrap();
+
+        let inputs: Vec<_> = ctx.inputs().collect();
+        assert_eq!(inputs.len(), 1);
+        assert_eq!(inputs[0].name.to_str().unwrap(), "dense_2_input");
+        assert_eq!(inputs[0].element_type, ElementType::Float32);
+        assert_eq!(inputs[0].shape.as_ref(), &[1, 1]);
+        let outputs: Vec<_> = ctx.outputs().collect();
+        assert_eq!(outputs[0].name.to_str().unwrap(), "Identity");
+        assert_eq!(ctx.opcount(), 3);
+    }
+
+    #[test]
+    fn outputs_come_from_the_callback() {
+        let mut ctx = sine()
+            .on_infer(|inputs, outputs| {
+                let x = inputs[0].as_slice::<f32>().unwrap()[0];
+                outputs[0].as_mut_slice::<f32>().unwrap()[0] = x * 2.0;
+                Ok(())
+            })
+            .build()
+            .unwrap();
+        let mut output = [0_f32];
+
+        ctx.infer(
+            &[Tensor::from_slice(&[21.0_f32], &[1, 1])],
+            &mut [TensorMut::from_slice(&mut output, &[1, 1])],
+        )
+        .unwrap();
+
+        assert_eq!(output, [42.0]);
+        assert_eq!(ctx.calls(), 1);
+    }
+
+    #[test]
+    fn outputs_are_zeroed_without_a_callback() {
+        let mut ctx = sine().build().unwrap();
+        let mut output = [1.0_f32];
+
+        ctx.infer(
+            &[Tensor::from_slice(&[0.5_f32], &[1, 1])],
+            &mut [TensorMut::from_slice(&mut output, &[1, 1])],
+        )
+        .unwrap();
+
+        assert_eq!(output, [0.0]);
+    }
+
+    #[test]
+    fn callback_errors_are_returned() {
+        let mut ctx = sine()
+            .on_infer(|_, _| Err(InferError::DelegateError))
+            .build()
+            .unwrap();
+        let mut output = [0_f32];
+
+        let err = ctx
+            .infer(
+                &[Tensor::from_slice(&[0.5_f32], &[1, 1])],
+                &mut [TensorMut::from_slice(&mut output, &[1, 1])],
+            )
+            .unwrap_err();
+
+        assert_eq!(err, InferError::DelegateError);
+        assert_eq!(ctx.calls(), 0);
+    }
+
+    #[test]
+    fn mismatched_tensors_are_rejected() {
+        let mut ctx = sine().build().unwrap();
+        let mut output = [0_f32];
+
+        let wrong_type = ctx.infer(
+            &[Tensor::from_slice(&[1_i32], &[1, 1])],
+            &mut [TensorMut::from_slice(&mut output, &[1, 1])],
+        );
+        assert_eq!(
+            wrong_type,
+            Err(InferError::Inputs(TensorMismatch::Tensor {
+                index: 0,
+                expected: ElementType::Float32,
+                expected_bytes: 4,
+                actual: ElementType::Int32,
+                actual_bytes: 4,
+            }))
+        );
+        let wrong_size = ctx.infer(
+            &[Tensor::from_slice(&[0.5_f32, 0.5], &[1, 2])],
+            &mut [TensorMut::from_slice(&mut output, &[1, 1])],
+        );
+        assert_eq!(
+            wrong_size,
+            Err(InferError::Inputs(TensorMismatch::Tensor {
+                index: 0,
+                expected: ElementType::Float32,
+                expected_bytes: 4,
+                actual: ElementType::Float32,
+                actual_bytes: 8,
+            }))
+        );
+        let missing_input = ctx.infer(&[], &mut [TensorMut::from_slice(&mut output, &[1, 1])]);
+        assert_eq!(
+            missing_input,
+            Err(InferError::Inputs(TensorMismatch::Count {
+                expected: 1,
+                actual: 0,
+            }))
+        );
+    }
+
+    #[test]
+    fn use_the_mock_as_a_backend() {
+        let mut backend: Box<dyn Backend> = Box::new(
+            sine()
+                .acceleration_backend(AccelerationBackend::EDGETPU)
+                .build()
+                .unwrap(),
+        );
+        let mut output = [1.0_f32];
+
+        backend
+            .infer(
+                &[Tensor::from_slice(&[0.5_f32], &[1, 1])],
+                &mut [TensorMut::from_slice(&mut output, &[1, 1])],
+            )
+            .unwrap();
+
+        assert_eq!(backend.inputs().len(), 1);
+        assert_eq!(backend.outputs()[0].name.to_str().unwrap(), "Identity");
+        assert_eq!(
+            backend.capabilities(),
+            Capabilities {
+                acceleration_backend: AccelerationBackend::EDGETPU,
+                profiling: false,
+                state: false,
+            }
+        );
+        assert_eq!(output, [0.0]);
+    }
+
+    #[test]
+    fn names_with_nul_bytes_are_invalid() {
+        let result = MockContext::builder()
+            .input("x\0", ElementType::Float32, &[1])
+            .build();
+
+        assert!(matches!(result, Err(Error::InvalidString(_))));
+    }
+}
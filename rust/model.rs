@@ -0,0 +1,556 @@
+use crate::{
+    context::check_load_result,
+    ffi,
+    metadata::{read_associated_file, TFLITE_METADATA},
+    slice_from_raw,
+    version::check_abi,
+    AssociatedFile, ElementType, LoadError, MetadataError, ModelMetadata, OpResolver,
+    TensorDescriptor, EDGETPU_CUSTOM_OP,
+};
+use itertools::Itertools;
+use std::{
+    borrow::Cow,
+    ffi::{CStr, CString},
+    fmt::{self, Debug, Formatter},
+    mem::MaybeUninit,
+    os::raw::c_int,
+    ptr::NonNull,
+};
+
+/// A TensorFlow Lite model which has been loaded and verified, but hasn't
+/// been turned into an interpreter.
+///
+/// This is useful for inspecting a model without paying the cost of
+/// allocating its tensors.
+pub struct Model {
+    model: NonNull<ffi::RuneCoralModel>,
+}
+
+impl Model {
+    /// Check that `model` is a well-formed TensorFlow Lite model without
+    /// loading it.
+    ///
+    /// This is much cheaper than [`Model::load()`] because the model isn't
+    /// copied and nothing is allocated.
+    pub fn verify(model: &[u8]) -> Result<ModelInfo, VerifyError> {
+        #[cfg(feature = "dynamic")]
+        crate::library::library().map_err(VerifyError::LibraryUnavailable)?;
+
+        let mut info = ffi::RuneCoralModelInfo::default();
+
+        let ret = unsafe {
+            ffi::verify_model(model.as_ptr().cast(), model.len() as ffi::size_t, &mut info)
+        };
+
+        let error = match ret {
+            ffi::RuneCoralVerifyResult__Ok => {
+                return Ok(ModelInfo::from_rune_coral_model_info(&info))
+            }
+            ffi::RuneCoralVerifyResult__Truncated => VerifyError::Truncated,
+            ffi::RuneCoralVerifyResult__BadIdentifier => VerifyError::BadIdentifier,
+            ffi::RuneCoralVerifyResult__SchemaVersionMismatch => {
+                VerifyError::SchemaVersionMismatch {
+                    version: info.schema_version,
+                    supported: info.supported_schema_version,
+                }
+            }
+            ffi::RuneCoralVerifyResult__BufferOutOfRange => VerifyError::BufferOutOfRange,
+            ffi::RuneCoralVerifyResult__Malformed => VerifyError::Malformed,
+            _ => VerifyError::Other { return_code: ret },
+        };
+
+        Err(error)
+    }
+
+    /// Load and verify a TensorFlow Lite model.
+    pub fn load(model: &[u8]) -> Result<Model, LoadError> {
+        #[cfg(feature = "dynamic")]
+        crate::library::check_available()?;
+        check_abi()?;
+
+        let mut loaded = MaybeUninit::uninit();
+
+        // Safety: the model is copied, so we don't need to worry about it
+        // outliving `model`.
+        unsafe {
+            let ret = ffi::create_model(
+                model.as_ptr().cast(),
+                model.len() as ffi::size_t,
+                loaded.as_mut_ptr(),
+            );
+
+            check_load_result(ret)?;
+
+            Ok(Model {
+                model: NonNull::new(loaded.assume_init()).expect("Should be initialized"),
+            })
+        }
+    }
+
+    /// The subgraphs that make up this model, where the first subgraph is
+    /// the one that gets executed.
+    pub fn subgraphs(&self) -> impl Iterator<Item = Subgraph<'_>> + '_ {
+        unsafe {
+            let mut subgraphs = MaybeUninit::uninit();
+            let len = ffi::model_subgraphs(self.model.as_ptr(), subgraphs.as_mut_ptr());
+
+            slice_from_raw(subgraphs.assume_init(), len as usize)
+                .iter()
+                .map(Subgraph::from_rune_coral_subgraph)
+        }
+    }
+
+    /// Was this model compiled with `edgetpu_compiler`?
+    ///
+    /// Compiled models can only be loaded with the
+    /// [`AccelerationBackend::EDGETPU`][crate::AccelerationBackend::EDGETPU]
+    /// backend on a machine with an Edge TPU, while other models always run
+    /// on the CPU.
+    pub fn requires_edgetpu(&self) -> bool {
+        self.subgraphs().any(|subgraph| {
+            subgraph
+                .operators
+                .iter()
+                .any(|op| op.custom_name.map(CStr::to_bytes) == Some(EDGETPU_CUSTOM_OP.as_bytes()))
+        })
+    }
+
+    /// Make sure every operator used by this model is one of TensorFlow
+    /// Lite's builtin operators.
+    ///
+    /// When the crate is built with the `selected_ops` feature, only the
+    /// operators which were selected at build time are available.
+    pub fn check_compatibility(&self) -> Result<(), UnsupportedOperators> {
+        self.unresolved_ops(std::ptr::null())
+    }
+
+    /// Make sure every operator used by this model can be resolved by an
+    /// [`OpResolver`].
+    pub fn check_compatibility_with(
+        &self,
+        resolver: &OpResolver,
+    ) -> Result<(), UnsupportedOperators> {
+        self.unresolved_ops(resolver.handle().as_ptr())
+    }
+
+    /// Get the raw metadata buffer with this name (e.g. `"TFLITE_METADATA"`),
+    /// if the model has one.
+    pub fn metadata_buffer(&self, name: &str) -> Option<&[u8]> {
+        let name = CString::new(name).ok()?;
+
+        unsafe {
+            let mut data = MaybeUninit::uninit();
+            let len = ffi::model_metadata(self.model.as_ptr(), name.as_ptr(), data.as_mut_ptr());
+
+            if len == 0 {
+                None
+            } else {
+                Some(slice_from_raw(data.assume_init().cast(), len as usize))
+            }
+        }
+    }
+
+    /// Read the [TensorFlow Lite Metadata][metadata] embedded in this model,
+    /// if it has any.
+    ///
+    /// [metadata]: https://www.tensorflow.org/lite/convert/metadata
+    pub fn metadata(&self) -> Result<Option<ModelMetadata>, MetadataError> {
+        self.metadata_buffer(TFLITE_METADATA)
+            .map(ModelMetadata::parse)
+            .transpose()
+    }
+
+    /// Read one of the files (e.g. `labels.txt`) packed into the model
+    /// alongside its metadata.
+    pub fn associated_file(&self, name: &str) -> Result<Vec<u8>, MetadataError> {
+        read_associated_file(self.buffer(), name)
+    }
+
+    /// Read a labels file (see [`crate::TensorMetadata::label_file()`]),
+    /// where each non-empty line is a label.
+    pub fn labels(&self, file: &AssociatedFile) -> Result<Vec<String>, MetadataError> {
+        let contents = self.associated_file(&file.name)?;
+
+        Ok(String::from_utf8_lossy(&contents)
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(String::from)
+            .collect())
+    }
+
+    /// The bytes this model was loaded from.
+    fn buffer(&self) -> &[u8] {
+        unsafe {
+            let mut data = MaybeUninit::uninit();
+            let len = ffi::model_buffer(self.model.as_ptr(), data.as_mut_ptr());
+            slice_from_raw(data.assume_init().cast(), len as usize)
+        }
+    }
+
+    fn unresolved_ops(
+        &self,
+        resolver: *const ffi::RuneCoralOpResolver,
+    ) -> Result<(), UnsupportedOperators> {
+        unsafe {
+            let len =
+                ffi::model_unresolved_ops(self.model.as_ptr(), resolver, std::ptr::null_mut(), 0);
+            if len == 0 {
+                return Ok(());
+            }
+
+            let mut ops = vec![ffi::RuneCoralOpCode::default(); len as usize];
+            let len = ffi::model_unresolved_ops(
+                self.model.as_ptr(),
+                resolver,
+                ops.as_mut_ptr(),
+                ops.len() as ffi::size_t,
+            );
+            ops.truncate(len as usize);
+
+            Err(UnsupportedOperators {
+                operators: ops
+                    .iter()
+                    .map(|op| UnsupportedOperator::from_rune_coral_op_code(op))
+                    .collect(),
+            })
+        }
+    }
+}
+
+/// Information about a model which passed [`Model::verify()`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ModelInfo {
+    /// The model's size in bytes.
+    pub size: usize,
+    /// The TensorFlow Lite schema version the model was written with.
+    pub schema_version: u32,
+    pub subgraphs: usize,
+    pub operator_codes: usize,
+    pub buffers: usize,
+}
+
+impl ModelInfo {
+    fn from_rune_coral_model_info(info: &ffi::RuneCoralModelInfo) -> Self {
+        ModelInfo {
+            size: info.size as usize,
+            schema_version: info.schema_version,
+            subgraphs: info.num_subgraphs as usize,
+            operator_codes: info.num_operator_codes as usize,
+            buffers: info.num_buffers as usize,
+        }
+    }
+}
+
+/// The reason a buffer isn't a valid TensorFlow Lite model.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum VerifyError {
+    /// The buffer ends before the model does.
+    #[error("The model is truncated")]
+    Truncated,
+    /// The buffer doesn't have the `"TFL3"` file identifier, so it probably
+    /// isn't a TensorFlow Lite model.
+    #[error("The buffer doesn't have the TensorFlow Lite file identifier")]
+    BadIdentifier,
+    #[error("The model uses schema version {version}, but only version {supported} is supported")]
+    SchemaVersionMismatch { version: u32, supported: u32 },
+    /// A tensor or metadata entry refers to a buffer that doesn't exist.
+    #[error("The model refers to a buffer which doesn't exist")]
+    BufferOutOfRange,
+    /// The flatbuffer failed verification for some other reason.
+    #[error("The model is malformed")]
+    Malformed,
+    /// `librunecoral` couldn't be loaded at runtime.
+    #[cfg(feature = "dynamic")]
+    #[error("librunecoral is unavailable")]
+    LibraryUnavailable(#[source] crate::library::LibraryError),
+    #[error("Unknown verification error {}", return_code)]
+    Other {
+        return_code: ffi::RuneCoralVerifyResult,
+    },
+}
+
+/// The operators used by a model which `librunecoral` isn't able to run.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+#[error("The model uses unsupported operators: {}", .operators.iter().join(", "))]
+pub struct UnsupportedOperators {
+    pub operators: Vec<UnsupportedOperator>,
+}
+
+/// An operator which `librunecoral` isn't able to run.
+#[derive(Debug, Clone, PartialEq)]
+pub enum UnsupportedOperator {
+    /// A builtin operator, or a version of it, which isn't available.
+    Builtin { name: String, version: i32 },
+    /// A custom operator which hasn't been registered.
+    Custom { name: String, version: i32 },
+}
+
+impl UnsupportedOperator {
+    /// Safety: the op code's strings must be null or valid C strings.
+    unsafe fn from_rune_coral_op_code(op: &ffi::RuneCoralOpCode) -> Self {
+        if op.custom_name.is_null() {
+            UnsupportedOperator::Builtin {
+                name: cstr_or_empty(op.op_name).to_string_lossy().into_owned(),
+                version: op.version,
+            }
+        } else {
+            UnsupportedOperator::Custom {
+                name: CStr::from_ptr(op.custom_name)
+                    .to_string_lossy()
+                    .into_owned(),
+                version: op.version,
+            }
+        }
+    }
+
+    /// Is this one of the TensorFlow operators which can only be executed by
+    /// the Flex delegate (i.e. a `Flex*` custom operator)?
+    pub fn requires_flex_delegate(&self) -> bool {
+        matches!(self, UnsupportedOperator::Custom { name, .. } if name.starts_with("Flex"))
+    }
+
+    /// Is this the operator `edgetpu_compiler` uses for the parts of a model
+    /// which run on the Edge TPU?
+    pub fn requires_edgetpu(&self) -> bool {
+        matches!(self, UnsupportedOperator::Custom { name, .. } if name == EDGETPU_CUSTOM_OP)
+    }
+}
+
+impl fmt::Display for UnsupportedOperator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            UnsupportedOperator::Builtin { name, version } => write!(f, "{} v{}", name, version),
+            UnsupportedOperator::Custom { name, version } => {
+                write!(f, "custom op \"{}\" v{}", name, version)?;
+
+                if self.requires_flex_delegate() {
+                    write!(f, " (requires the Flex delegate)")?;
+                }
+                if self.requires_edgetpu() {
+                    write!(f, " (requires an Edge TPU)")?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Debug for Model {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Model").finish_non_exhaustive()
+    }
+}
+
+impl Drop for Model {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::destroy_model(self.model.as_ptr());
+        }
+    }
+}
+
+// Safety: A model is never mutated after it has been loaded, so it can be
+// moved between and accessed from multiple threads.
+unsafe impl Send for Model {}
+unsafe impl Sync for Model {}
+
+/// Safety: the pointer must be null or point to a valid C string that
+/// outlives `'a`.
+unsafe fn cstr_or_empty<'a>(ptr: *const std::os::raw::c_char) -> &'a CStr {
+    if ptr.is_null() {
+        CStr::from_bytes_with_nul(b"\0").unwrap()
+    } else {
+        CStr::from_ptr(ptr)
+    }
+}
+
+/// A graph of operators and the tensors that connect them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Subgraph<'a> {
+    pub name: &'a CStr,
+    pub tensors: Vec<GraphTensor<'a>>,
+    /// The subgraph's operators, in the order they are stored in the model.
+    pub operators: Vec<Operator<'a>>,
+    /// Indices into [`Subgraph::tensors`] for the subgraph's inputs.
+    pub inputs: &'a [c_int],
+    /// Indices into [`Subgraph::tensors`] for the subgraph's outputs.
+    pub outputs: &'a [c_int],
+}
+
+impl<'a> Subgraph<'a> {
+    fn from_rune_coral_subgraph(subgraph: &'a ffi::RuneCoralSubgraph) -> Self {
+        // Safety: Lifetimes ensure our Subgraph won't accidentally outlive
+        // the model it came from.
+        unsafe {
+            Subgraph {
+                name: cstr_or_empty(subgraph.name),
+                tensors: slice_from_raw(subgraph.tensors, subgraph.num_tensors as usize)
+                    .iter()
+                    .map(GraphTensor::from_rune_coral_graph_tensor)
+                    .collect(),
+                operators: slice_from_raw(subgraph.operators, subgraph.num_operators as usize)
+                    .iter()
+                    .map(Operator::from_rune_coral_operator)
+                    .collect(),
+                inputs: slice_from_raw(subgraph.inputs, subgraph.num_inputs as usize),
+                outputs: slice_from_raw(subgraph.outputs, subgraph.num_outputs as usize),
+            }
+        }
+    }
+
+    /// Look up one of this subgraph's tensors by index, returning `None` for
+    /// omitted optional inputs (`-1`) or out-of-range indices.
+    pub fn tensor(&self, index: c_int) -> Option<&GraphTensor<'a>> {
+        if index < 0 {
+            return None;
+        }
+
+        self.tensors.get(index as usize)
+    }
+}
+
+/// A tensor, as it is described by the model.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphTensor<'a> {
+    pub name: &'a CStr,
+    pub element_type: ElementType,
+    pub shape: Cow<'a, [c_int]>,
+    /// The tensor's quantization parameters, if it is quantized.
+    pub quantization: Option<Quantization<'a>>,
+    /// Index of the model buffer backing this tensor, where `0` means there
+    /// is no constant data.
+    pub buffer: usize,
+    /// The number of bytes of constant data stored in the model for this
+    /// tensor (e.g. weights).
+    pub buffer_size: usize,
+    /// Does this tensor hold state between inference runs?
+    pub is_variable: bool,
+}
+
+impl<'a> GraphTensor<'a> {
+    fn from_rune_coral_graph_tensor(tensor: &'a ffi::RuneCoralGraphTensor) -> Self {
+        unsafe {
+            let q = &tensor.quantization;
+            let quantization = if q.count > 0 {
+                Some(Quantization {
+                    scale: slice_from_raw(q.scale, q.count as usize),
+                    zero_point: slice_from_raw(q.zero_point, q.count as usize),
+                    quantized_dimension: q.quantized_dimension as usize,
+                })
+            } else {
+                None
+            };
+
+            GraphTensor {
+                name: cstr_or_empty(tensor.name),
+                element_type: ElementType::from(tensor.type_),
+                shape: Cow::Borrowed(slice_from_raw(tensor.shape, tensor.rank as usize)),
+                quantization,
+                buffer: tensor.buffer as usize,
+                buffer_size: tensor.buffer_size as usize,
+                is_variable: tensor.is_variable != 0,
+            }
+        }
+    }
+
+    /// Get a [`TensorDescriptor`] that describes this tensor.
+    pub fn descriptor(&self) -> TensorDescriptor<'_> {
+        TensorDescriptor {
+            name: self.name,
+            element_type: self.element_type,
+            shape: Cow::Borrowed(&self.shape),
+        }
+    }
+}
+
+/// Affine quantization parameters, where
+/// `real_value = scale * (quantized_value - zero_point)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quantization<'a> {
+    /// One scale per channel, or a single scale for per-tensor quantization.
+    pub scale: &'a [f32],
+    /// One zero point per channel, or a single zero point for per-tensor
+    /// quantization.
+    pub zero_point: &'a [i64],
+    /// The dimension per-channel quantization is applied along.
+    pub quantized_dimension: usize,
+}
+
+/// A single node in a [`Subgraph`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Operator<'a> {
+    /// The operator's `tflite::BuiltinOperator` code.
+    pub builtin_code: i32,
+    /// The builtin operator's name (e.g. `"CONV_2D"`, or `"CUSTOM"`).
+    pub name: &'a CStr,
+    /// The custom operator's name, if this isn't a builtin operator.
+    pub custom_name: Option<&'a CStr>,
+    pub version: i32,
+    /// Indices into [`Subgraph::tensors`], where `-1` marks an omitted
+    /// optional input.
+    pub inputs: &'a [c_int],
+    /// Indices into [`Subgraph::tensors`].
+    pub outputs: &'a [c_int],
+}
+
+impl<'a> Operator<'a> {
+    fn from_rune_coral_operator(op: &'a ffi::RuneCoralOperator) -> Self {
+        unsafe {
+            Operator {
+                builtin_code: op.builtin_code,
+                name: cstr_or_empty(op.op_name),
+                custom_name: if op.custom_name.is_null() {
+                    None
+                } else {
+                    Some(CStr::from_ptr(op.custom_name))
+                },
+                version: op.version,
+                inputs: slice_from_raw(op.inputs, op.num_inputs as usize),
+                outputs: slice_from_raw(op.outputs, op.num_outputs as usize),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn model_is_send_and_sync() {
+        static_assertions::assert_impl_all!(Model: Send, Sync);
+    }
+
+    #[test]
+    fn unsupported_operators_are_listed_by_name() {
+        let err = UnsupportedOperators {
+            operators: vec![
+                UnsupportedOperator::Builtin {
+                    name: "CONV_2D".to_string(),
+                    version: 5,
+                },
+                UnsupportedOperator::Custom {
+                    name: "FlexRFFT".to_string(),
+                    version: 1,
+                },
+                UnsupportedOperator::Custom {
+                    name: "AudioSpectrogram".to_string(),
+                    version: 1,
+                },
+                UnsupportedOperator::Custom {
+                    name: "edgetpu-custom-op".to_string(),
+                    version: 1,
+                },
+            ],
+        };
+
+        assert_eq!(
+            err.to_string(),
+            "The model uses unsupported operators: CONV_2D v5, custom op \"FlexRFFT\" v1 \
+             (requires the Flex delegate), custom op \"AudioSpectrogram\" v1, custom op \
+             \"edgetpu-custom-op\" v1 (requires an Edge TPU)"
+        );
+    }
+}
@@ -0,0 +1,372 @@
+use crate::{ffi, slice_from_raw, Error, LoadError, Tensor, TensorDescriptor, TensorMut};
+use std::{
+    ffi::{CStr, CString},
+    fmt::{self, Debug, Formatter},
+    os::raw::c_void,
+    panic::{self, AssertUnwindSafe},
+    ptr::NonNull,
+    sync::Arc,
+};
+
+/// The error type returned by a [`CustomOp`].
+pub type CustomOpError = Box<dyn std::error::Error + Send + Sync>;
+
+/// An operator implemented in Rust, which can be used to run a model's
+/// custom ops.
+///
+/// Closures with the same signature as [`CustomOp::invoke()`] also implement
+/// this trait.
+pub trait CustomOp: Send + Sync + 'static {
+    /// Check the node's tensors once their shapes are known, before any
+    /// inference is done.
+    ///
+    /// The outputs can't be resized here, so the model must already specify
+    /// the shape of each output.
+    fn prepare(
+        &self,
+        inputs: &[TensorDescriptor<'_>],
+        outputs: &[TensorDescriptor<'_>],
+    ) -> Result<(), CustomOpError> {
+        let _ = (inputs, outputs);
+        Ok(())
+    }
+
+    /// Run the operator, reading from `inputs` and writing to `outputs`.
+    fn invoke(
+        &self,
+        inputs: &[Tensor<'_>],
+        outputs: &mut [TensorMut<'_>],
+    ) -> Result<(), CustomOpError>;
+}
+
+impl<F> CustomOp for F
+where
+    F: Fn(&[Tensor<'_>], &mut [TensorMut<'_>]) -> Result<(), CustomOpError> + Send + Sync + 'static,
+{
+    fn invoke(
+        &self,
+        inputs: &[Tensor<'_>],
+        outputs: &mut [TensorMut<'_>],
+    ) -> Result<(), CustomOpError> {
+        self(inputs, outputs)
+    }
+}
+
+/// Resolves the operators used by a model to their implementations.
+///
+/// By default only TensorFlow Lite's builtin operators are available, but
+/// custom operators can be added with [`OpResolver::add_custom()`].
+pub struct OpResolver {
+    handle: Arc<ResolverHandle>,
+    /// Every custom op registered so far, so they can be added to a fresh
+    /// resolver when the current one is shared.
+    custom_ops: Vec<Registration>,
+}
+
+type Registration = Box<dyn Fn(&mut ResolverHandle) -> Result<(), Error> + Send + Sync>;
+
+impl OpResolver {
+    /// Create an [`OpResolver`] which knows about all builtin operators.
+    ///
+    /// This fails if `librunecoral` can't be loaded.
+    pub fn new() -> Result<Self, LoadError> {
+        Ok(OpResolver {
+            handle: Arc::new(ResolverHandle::new()?),
+            custom_ops: Vec::new(),
+        })
+    }
+
+    /// Register a custom operator, which will be used for any custom op in
+    /// the model with the same name and version.
+    ///
+    /// Builders and contexts which are already using this resolver won't see
+    /// the new operator.
+    pub fn add_custom<O: CustomOp>(
+        &mut self,
+        name: &str,
+        version: i32,
+        op: O,
+    ) -> Result<(), Error> {
+        let name = CString::new(name)?;
+        let op = Arc::new(op);
+        let register: Registration =
+            Box::new(move |handle| handle.add_custom(&name, version, SharedOp(Arc::clone(&op))));
+
+        register(self.handle_mut()?)?;
+        self.custom_ops.push(register);
+
+        Ok(())
+    }
+
+    pub(crate) fn handle(&self) -> Arc<ResolverHandle> {
+        Arc::clone(&self.handle)
+    }
+
+    /// Get exclusive access to the resolver, copying it if a
+    /// [`crate::ContextBuilder`] or [`crate::InferenceContext`] is also
+    /// using it.
+    fn handle_mut(&mut self) -> Result<&mut ResolverHandle, Error> {
+        if Arc::get_mut(&mut self.handle).is_none() {
+            let mut handle = ResolverHandle::new()?;
+            for register in &self.custom_ops {
+                register(&mut handle)?;
+            }
+            self.handle = Arc::new(handle);
+        }
+
+        Ok(Arc::get_mut(&mut self.handle).expect("The handle is no longer shared"))
+    }
+}
+
+impl Debug for OpResolver {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OpResolver").finish_non_exhaustive()
+    }
+}
+
+/// Owns a `RuneCoralOpResolver`, destroying it once the last [`OpResolver`]
+/// or [`crate::InferenceContext`] using it goes away.
+pub(crate) struct ResolverHandle(NonNull<ffi::RuneCoralOpResolver>);
+
+impl ResolverHandle {
+    fn new() -> Result<Self, LoadError> {
+        #[cfg(feature = "dynamic")]
+        crate::library::check_available()?;
+
+        let resolver = unsafe { ffi::create_op_resolver() };
+
+        NonNull::new(resolver)
+            .map(ResolverHandle)
+            .ok_or(LoadError::InternalError)
+    }
+
+    pub(crate) fn as_ptr(&self) -> *const ffi::RuneCoralOpResolver {
+        self.0.as_ptr()
+    }
+
+    fn add_custom<O: CustomOp>(&mut self, name: &CStr, version: i32, op: O) -> Result<(), Error> {
+        let op = ffi::RuneCoralCustomOp {
+            user_data: Box::into_raw(Box::new(op)).cast(),
+            prepare: Some(prepare::<O>),
+            invoke: Some(invoke::<O>),
+            destroy: Some(destroy::<O>),
+        };
+
+        // Safety: librunecoral takes ownership of our boxed op and will call
+        // destroy() when it is no longer needed, and `&mut self` ensures
+        // nobody can be building an interpreter with the resolver while we
+        // register the op.
+        let ret =
+            unsafe { ffi::op_resolver_add_custom(self.0.as_ptr(), name.as_ptr(), version, op) };
+
+        if ret == 0 {
+            Ok(())
+        } else {
+            Err(Error::CustomOp {
+                name: name.to_string_lossy().into_owned(),
+            })
+        }
+    }
+}
+
+impl Drop for ResolverHandle {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::destroy_op_resolver(self.0.as_ptr());
+        }
+    }
+}
+
+// Safety: The resolver is only mutated through `&mut ResolverHandle`, which
+// OpResolver only hands out when nobody else holds a reference to the handle,
+// and our custom ops are all Send + Sync.
+unsafe impl Send for ResolverHandle {}
+unsafe impl Sync for ResolverHandle {}
+
+/// A [`CustomOp`] which may be registered with several resolvers.
+struct SharedOp<O>(Arc<O>);
+
+impl<O: CustomOp> CustomOp for SharedOp<O> {
+    fn prepare(
+        &self,
+        inputs: &[TensorDescriptor<'_>],
+        outputs: &[TensorDescriptor<'_>],
+    ) -> Result<(), CustomOpError> {
+        self.0.prepare(inputs, outputs)
+    }
+
+    fn invoke(
+        &self,
+        inputs: &[Tensor<'_>],
+        outputs: &mut [TensorMut<'_>],
+    ) -> Result<(), CustomOpError> {
+        self.0.invoke(inputs, outputs)
+    }
+}
+
+fn to_inference_result(
+    result: std::thread::Result<Result<(), CustomOpError>>,
+) -> ffi::RuneCoralInferenceResult {
+    match result {
+        Ok(Ok(())) => ffi::RuneCoralInferenceResult__Ok,
+        // Note: We don't have a way to pass the error message back through
+        // the interpreter. Panics must never unwind into C++, though.
+        Ok(Err(_)) | Err(_) => ffi::RuneCoralInferenceResult__Error,
+    }
+}
+
+unsafe extern "C" fn prepare<O: CustomOp>(
+    user_data: *mut c_void,
+    inputs: *const ffi::RuneCoralTensor,
+    num_inputs: ffi::size_t,
+    outputs: *const ffi::RuneCoralTensor,
+    num_outputs: ffi::size_t,
+) -> ffi::RuneCoralInferenceResult {
+    let op = &*(user_data as *const O);
+    let inputs: Vec<_> = slice_from_raw(inputs, num_inputs as usize)
+        .iter()
+        .map(TensorDescriptor::from_rune_coral_tensor)
+        .collect();
+    let outputs: Vec<_> = slice_from_raw(outputs, num_outputs as usize)
+        .iter()
+        .map(TensorDescriptor::from_rune_coral_tensor)
+        .collect();
+
+    to_inference_result(panic::catch_unwind(AssertUnwindSafe(|| {
+        op.prepare(&inputs, &outputs)
+    })))
+}
+
+unsafe extern "C" fn invoke<O: CustomOp>(
+    user_data: *mut c_void,
+    inputs: *const ffi::RuneCoralTensor,
+    num_inputs: ffi::size_t,
+    outputs: *mut ffi::RuneCoralTensor,
+    num_outputs: ffi::size_t,
+) -> ffi::RuneCoralInferenceResult {
+    let op = &*(user_data as *const O);
+    let inputs: Vec<_> = slice_from_raw(inputs, num_inputs as usize)
+        .iter()
+        .map(|t| Tensor::from_rune_coral_tensor(t))
+        .collect();
+    let mut outputs: Vec<_> = if num_outputs > 0 {
+        std::slice::from_raw_parts_mut(outputs, num_outputs as usize)
+            .iter_mut()
+            .map(|t| TensorMut::from_rune_coral_tensor(t))
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    to_inference_result(panic::catch_unwind(AssertUnwindSafe(|| {
+        op.invoke(&inputs, &mut outputs)
+    })))
+}
+
+unsafe extern "C" fn destroy<O: CustomOp>(user_data: *mut c_void) {
+    drop(Box::from_raw(user_data as *mut O));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ElementType;
+
+    fn double(inputs: &[Tensor<'_>], outputs: &mut [TensorMut<'_>]) -> Result<(), CustomOpError> {
+        let input = inputs[0].as_slice::<f32>()?;
+        let output = outputs[0].as_mut_slice::<f32>()?;
+
+        for (o, i) in output.iter_mut().zip(input) {
+            *o = i * 2.0;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn adding_ops_to_a_shared_resolver_copies_it() {
+        let mut resolver = OpResolver::new().unwrap();
+        resolver.add_custom("DOUBLE", 1, double).unwrap();
+        let shared = resolver.handle();
+
+        resolver.add_custom("TRIPLE", 1, double).unwrap();
+
+        assert!(!Arc::ptr_eq(&shared, &resolver.handle));
+        assert_eq!(resolver.custom_ops.len(), 2);
+    }
+
+    #[test]
+    fn adding_ops_to_an_unshared_resolver_reuses_it() {
+        let mut resolver = OpResolver::new().unwrap();
+        let original = Arc::as_ptr(&resolver.handle);
+
+        resolver.add_custom("DOUBLE", 1, double).unwrap();
+        resolver.add_custom("TRIPLE", 1, double).unwrap();
+
+        assert_eq!(Arc::as_ptr(&resolver.handle), original);
+    }
+
+    #[test]
+    fn invoke_a_custom_op_through_its_callbacks() {
+        type Double = fn(&[Tensor<'_>], &mut [TensorMut<'_>]) -> Result<(), CustomOpError>;
+
+        let op = Box::into_raw(Box::new(double as Double)).cast::<c_void>();
+        let input = [1.0_f32, 2.0];
+        let mut output = [0.0_f32; 2];
+        let mut output_tensor = TensorMut::from_slice(&mut output, &[2]);
+
+        let input_tensor = Tensor::from_slice(&input, &[2]);
+
+        let ret = unsafe {
+            let inputs = [input_tensor.as_coral_tensor()];
+            let mut outputs = [output_tensor.as_coral_tensor()];
+
+            let ret = invoke::<Double>(op, inputs.as_ptr(), 1, outputs.as_mut_ptr(), 1);
+            destroy::<Double>(op);
+            ret
+        };
+
+        assert_eq!(ret, ffi::RuneCoralInferenceResult__Ok);
+        assert_eq!(output, [2.0, 4.0]);
+    }
+
+    #[test]
+    fn custom_op_errors_and_panics_are_reported_as_failures() {
+        struct Failing;
+
+        impl CustomOp for Failing {
+            fn prepare(
+                &self,
+                inputs: &[TensorDescriptor<'_>],
+                _outputs: &[TensorDescriptor<'_>],
+            ) -> Result<(), CustomOpError> {
+                assert_eq!(inputs[0].element_type, ElementType::Float32);
+                Err("Unsupported shape".into())
+            }
+
+            fn invoke(
+                &self,
+                _: &[Tensor<'_>],
+                _: &mut [TensorMut<'_>],
+            ) -> Result<(), CustomOpError> {
+                panic!("Oops")
+            }
+        }
+
+        let op = Box::into_raw(Box::new(Failing)).cast::<c_void>();
+        let input = [1.0_f32];
+        let input_tensor = Tensor::from_slice(&input, &[1]);
+
+        unsafe {
+            let inputs = [input_tensor.as_coral_tensor()];
+
+            let ret = prepare::<Failing>(op, inputs.as_ptr(), 1, std::ptr::null(), 0);
+            assert_eq!(ret, ffi::RuneCoralInferenceResult__Error);
+
+            let ret = invoke::<Failing>(op, inputs.as_ptr(), 1, std::ptr::null_mut(), 0);
+            assert_eq!(ret, ffi::RuneCoralInferenceResult__Error);
+
+            destroy::<Failing>(op);
+        }
+    }
+}
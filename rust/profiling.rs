@@ -0,0 +1,111 @@
+#[cfg(feature = "native")]
+use crate::ffi;
+use serde_json::json;
+#[cfg(feature = "native")]
+use std::ffi::CStr;
+use std::time::Duration;
+
+/// Per-operator timings recorded during a single call to
+/// [`InferenceContext::infer()`][crate::InferenceContext::infer].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Profile {
+    pub events: Vec<OpEvent>,
+}
+
+impl Profile {
+    /// Create a [`Profile`] from the events recorded by `librunecoral`.
+    ///
+    /// # Safety
+    ///
+    /// Each event's `op_name` must be null or point to a valid C string.
+    #[cfg(feature = "native")]
+    pub(crate) unsafe fn from_events(events: &[ffi::RuneCoralProfileEvent]) -> Self {
+        let events = events
+            .iter()
+            .map(|event| OpEvent {
+                op_name: if event.op_name.is_null() {
+                    String::new()
+                } else {
+                    CStr::from_ptr(event.op_name).to_string_lossy().into_owned()
+                },
+                builtin_code: event.builtin_code,
+                subgraph_index: event.subgraph_index as usize,
+                node_index: event.node_index as usize,
+                placement: if event.delegated != 0 {
+                    Placement::Delegate
+                } else {
+                    Placement::Cpu
+                },
+                start: Duration::from_micros(event.start_us),
+                duration: Duration::from_micros(event.duration_us),
+            })
+            .collect();
+
+        Profile { events }
+    }
+
+    /// The total time spent executing operators.
+    pub fn total_duration(&self) -> Duration {
+        self.events.iter().map(|e| e.duration).sum()
+    }
+
+    /// Export the events in the [Chrome Trace Event Format][format], suitable
+    /// for loading into `chrome://tracing` or [Perfetto](https://ui.perfetto.dev/).
+    ///
+    /// [format]: https://docs.google.com/document/d/1CvAClvFfyA5R-PhYUmn5OOQtYMH4h6I0nSsKchNAySU
+    pub fn to_chrome_trace(&self) -> String {
+        let trace_events: Vec<_> = self
+            .events
+            .iter()
+            .map(|event| {
+                json!({
+                    "name": event.op_name,
+                    "cat": event.placement.to_string(),
+                    "ph": "X",
+                    "ts": event.start.as_micros() as u64,
+                    "dur": event.duration.as_micros() as u64,
+                    "pid": 0,
+                    "tid": event.subgraph_index,
+                    "args": {
+                        "builtin_code": event.builtin_code,
+                        "node_index": event.node_index,
+                    },
+                })
+            })
+            .collect();
+
+        json!({ "traceEvents": trace_events }).to_string()
+    }
+}
+
+/// A single operator invocation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpEvent {
+    /// The operator's name (e.g. `"CONV_2D"`), or the name of the custom op
+    /// or delegate that executed it.
+    pub op_name: String,
+    /// The operator's `tflite::BuiltinOperator` code.
+    pub builtin_code: i32,
+    pub subgraph_index: usize,
+    pub node_index: usize,
+    pub placement: Placement,
+    /// When the operator started, relative to the start of the run.
+    pub start: Duration,
+    pub duration: Duration,
+}
+
+/// Where an operator was executed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Placement {
+    Cpu,
+    Delegate,
+}
+
+impl std::fmt::Display for Placement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Placement::Cpu => f.write_str("cpu"),
+            Placement::Delegate => f.write_str("delegate"),
+        }
+    }
+}
@@ -0,0 +1,40 @@
+/// A snapshot of an [`InferenceContext`][crate::InferenceContext]'s variable
+/// tensors (e.g. the hidden state of a recurrent model).
+///
+/// This lets one context serve several independent streams of input by
+/// swapping each stream's state in and out between calls to
+/// [`InferenceContext::infer()`][crate::InferenceContext::infer].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ContextState {
+    tensors: Vec<Vec<u8>>,
+}
+
+impl ContextState {
+    /// Create a [`ContextState`] from the raw contents of each variable
+    /// tensor, in the order they are used by the model.
+    pub fn new(tensors: Vec<Vec<u8>>) -> Self {
+        ContextState { tensors }
+    }
+
+    /// The raw contents of each variable tensor.
+    pub fn tensors(&self) -> &[Vec<u8>] {
+        &self.tensors
+    }
+
+    pub fn into_tensors(self) -> Vec<Vec<u8>> {
+        self.tensors
+    }
+}
+
+/// The reasons a [`ContextState`] can't be restored.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum RestoreError {
+    #[error("Expected state for {expected} variable tensors, but found {actual}")]
+    TensorCount { expected: usize, actual: usize },
+    #[error("Expected variable tensor {index} to contain {expected} bytes, but found {actual}")]
+    TensorSize {
+        index: usize,
+        expected: usize,
+        actual: usize,
+    },
+}
@@ -1,7 +1,7 @@
 use std::{borrow::Cow, fmt, os::raw::c_int};
 use itertools::Itertools;
 use std::ffi::CStr;
-use crate::ffi;
+use crate::{ffi, slice_from_raw};
 
 /// The shape and element type of a [`Tensor`].
 #[derive(Debug, Clone, PartialEq)]
@@ -17,7 +17,7 @@ pub struct TensorDescriptor<'a> {
 }
 
 /// Possible element types that can be used in a [`Tensor`].
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 #[non_exhaustive]
 pub enum ElementType {
     NoType = ffi::RuneCoralElementType__NoType as isize,
@@ -38,8 +38,9 @@ pub enum ElementType {
 /// A Rust type that can be used as the element of a tensor.
 ///
 /// This is an internal implementation detail and you shouldn't need to refer
-/// to it directly.
-pub trait TensorElement: Sized {
+/// to it directly. The trait is sealed because [`TensorElement::from_byte_buffer()`]
+/// is only sound for types where every bit pattern is valid.
+pub trait TensorElement: private::Sealed + Sized {
     const ELEMENT_TYPE: ElementType;
 
     /// Reinterpret a slice of this [`TensorElement`] as an immutable byte array.
@@ -47,11 +48,163 @@ pub trait TensorElement: Sized {
 
     /// Reinterpret a slice of this [`TensorElement`] as a mutable byte array.
     fn byte_buffer_mut(slice: &mut [Self]) -> &mut [u8];
+
+    /// Reinterpret an immutable byte array as a slice of this
+    /// [`TensorElement`], checking that it is correctly aligned and sized.
+    fn from_byte_buffer(buffer: &[u8]) -> Result<&[Self], TypeMismatch>;
+
+    /// Reinterpret a mutable byte array as a slice of this [`TensorElement`],
+    /// checking that it is correctly aligned and sized.
+    fn from_byte_buffer_mut(buffer: &mut [u8]) -> Result<&mut [Self], TypeMismatch>;
+}
+
+/// The reason a tensor's buffer couldn't be viewed as a slice of some
+/// [`TensorElement`].
+#[derive(Debug, Copy, Clone, PartialEq, thiserror::Error)]
+pub enum TypeMismatch {
+    #[error("Expected a tensor of {expected}, but it contains {actual}")]
+    ElementType {
+        expected: ElementType,
+        actual: ElementType,
+    },
+    #[error("The buffer isn't aligned to a multiple of {alignment} bytes")]
+    Misaligned { alignment: usize },
+    #[error("The buffer's length ({length} bytes) isn't a multiple of the element size ({element_size} bytes)")]
+    Length { length: usize, element_size: usize },
+}
+
+/// Make sure a buffer starting at `ptr` can hold a whole number of correctly
+/// aligned `T`'s, returning the number of elements.
+fn check_layout<T>(ptr: *const u8, length: usize) -> Result<usize, TypeMismatch> {
+    let alignment = std::mem::align_of::<T>();
+    let element_size = std::mem::size_of::<T>();
+
+    if ptr.align_offset(alignment) != 0 {
+        return Err(TypeMismatch::Misaligned { alignment });
+    }
+    if !length.is_multiple_of(element_size) {
+        return Err(TypeMismatch::Length { length, element_size });
+    }
+
+    Ok(length / element_size)
+}
+
+/// The reason some tensors can't be used as a model's inputs or outputs.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum TensorMismatch {
+    #[error("Expected {expected} tensors, but {actual} were provided")]
+    Count { expected: usize, actual: usize },
+    #[error("Expected tensor {index} to be {expected_bytes} bytes of {expected}, but it is {actual_bytes} bytes of {actual}")]
+    Tensor {
+        index: usize,
+        expected: ElementType,
+        expected_bytes: usize,
+        actual: ElementType,
+        actual_bytes: usize,
+    },
+}
+
+/// Make sure `inputs` have the element types and sizes a model's inputs
+/// expect.
+///
+/// ```rust
+/// use hotg_runecoral::{validate_inputs, ElementType, Tensor, TensorDescriptor, TensorMismatch};
+/// use std::{borrow::Cow, ffi::CStr};
+///
+/// let descriptor = TensorDescriptor {
+///     name: CStr::from_bytes_with_nul(b"input\0").unwrap(),
+///     element_type: ElementType::Float32,
+///     shape: Cow::Borrowed(&[1, 2]),
+/// };
+/// let input = [0.5_f32];
+///
+/// let err = validate_inputs(&[Tensor::from_slice(&input, &[1])], vec![descriptor]).unwrap_err();
+/// assert_eq!(
+///     err,
+///     TensorMismatch::Tensor {
+///         index: 0,
+///         expected: ElementType::Float32,
+///         expected_bytes: 8,
+///         actual: ElementType::Float32,
+///         actual_bytes: 4,
+///     }
+/// );
+/// ```
+pub fn validate_inputs<'a>(
+    inputs: &[Tensor<'_>],
+    descriptors: impl IntoIterator<Item = TensorDescriptor<'a>>,
+) -> Result<(), TensorMismatch> {
+    let expected: Vec<_> = descriptors
+        .into_iter()
+        .map(|d| (d.element_type, d.byte_len()))
+        .collect();
+
+    check_tensors(
+        expected.into_iter(),
+        inputs.iter().map(|t| (t.element_type, t.buffer.len())),
+    )
+}
+
+/// Make sure each tensor has the `(element_type, bytes)` that is expected.
+///
+/// Strings don't have a fixed size, so only their element type is checked.
+pub(crate) fn check_tensors(
+    expected: impl ExactSizeIterator<Item = (ElementType, usize)>,
+    actual: impl ExactSizeIterator<Item = (ElementType, usize)>,
+) -> Result<(), TensorMismatch> {
+    if expected.len() != actual.len() {
+        return Err(TensorMismatch::Count {
+            expected: expected.len(),
+            actual: actual.len(),
+        });
+    }
+
+    for (index, ((expected, expected_bytes), (actual, actual_bytes))) in
+        expected.zip(actual).enumerate()
+    {
+        let sized = expected.size() != 0;
+
+        if expected != actual || (sized && expected_bytes != actual_bytes) {
+            return Err(TensorMismatch::Tensor {
+                index,
+                expected,
+                expected_bytes,
+                actual,
+                actual_bytes,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Decode a tensor's buffer as a sequence of `$ty`s, converting each one
+/// with `$convert`.
+#[cfg(feature = "native")]
+macro_rules! decode_elements {
+    ($buffer:expr, $ty:ty, |$v:ident| $convert:expr) => {
+        $buffer
+            .chunks_exact(std::mem::size_of::<$ty>())
+            .map(|chunk| {
+                let $v = <$ty>::from_ne_bytes(std::convert::TryInto::try_into(chunk).unwrap());
+                $convert
+            })
+            .collect()
+    };
+}
+
+#[cfg(feature = "native")]
+pub(crate) use decode_elements;
+
+mod private {
+    pub trait Sealed {}
 }
 
 macro_rules! impl_tensor_element {
     ($($type:ty => $variant:expr,)* $(,)?) => {
         $(
+            impl private::Sealed for $type {}
+
             impl TensorElement  for $type {
                 const ELEMENT_TYPE: ElementType = $variant;
 
@@ -68,6 +221,24 @@ macro_rules! impl_tensor_element {
                         std::slice::from_raw_parts_mut(slice.as_mut_ptr().cast(), len)
                     }
                 }
+
+                fn from_byte_buffer(buffer: &[u8]) -> Result<&[Self], TypeMismatch> {
+                    let len = check_layout::<Self>(buffer.as_ptr(), buffer.len())?;
+                    // Safety: the buffer is aligned and sized correctly, and
+                    // every bit pattern is a valid $type.
+                    unsafe {
+                        Ok(std::slice::from_raw_parts(buffer.as_ptr().cast(), len))
+                    }
+                }
+
+                fn from_byte_buffer_mut(buffer: &mut [u8]) -> Result<&mut [Self], TypeMismatch> {
+                    let len = check_layout::<Self>(buffer.as_ptr(), buffer.len())?;
+                    // Safety: the buffer is aligned and sized correctly, and
+                    // every bit pattern is a valid $type.
+                    unsafe {
+                        Ok(std::slice::from_raw_parts_mut(buffer.as_mut_ptr().cast(), len))
+                    }
+                }
             }
         )*
     };
@@ -82,6 +253,21 @@ impl_tensor_element! {
     f64 => ElementType::Float64,
 }
 
+impl ElementType {
+    /// The number of bytes used by a single element, or `0` for types which
+    /// don't have a fixed size.
+    pub fn size(self) -> usize {
+        match self {
+            ElementType::Bool | ElementType::UInt8 | ElementType::Int8 => 1,
+            ElementType::Int16 | ElementType::Float16 => 2,
+            ElementType::Int32 | ElementType::Float32 => 4,
+            ElementType::Int64 | ElementType::Float64 | ElementType::Complex64 => 8,
+            ElementType::Complex128 => 16,
+            ElementType::NoType | ElementType::String => 0,
+        }
+    }
+}
+
 impl From<ElementType> for ffi::RuneCoralElementType {
     fn from(e: ElementType) -> ffi::RuneCoralElementType {
         e as ffi::RuneCoralElementType
@@ -117,13 +303,21 @@ impl<'a> TensorDescriptor<'a> {
             TensorDescriptor {
                 name: if tensor.name != std::ptr::null() { CStr::from_ptr(tensor.name) } else { CStr::from_bytes_with_nul(b"\0").unwrap() },
                 element_type: ElementType::from(tensor.type_),
-                shape: Cow::Borrowed(std::slice::from_raw_parts(
-                    tensor.shape,
-                    tensor.rank as usize,
-                )),
+                shape: Cow::Borrowed(slice_from_raw(tensor.shape, tensor.rank as usize)),
             }
         }
     }
+
+    /// The number of bytes needed to store a tensor with this shape and
+    /// element type.
+    pub fn byte_len(&self) -> usize {
+        self.dimensions().iter().product::<usize>() * self.element_type.size()
+    }
+
+    /// The tensor's shape, treating unknown (negative) dimensions as zero.
+    pub fn dimensions(&self) -> Vec<usize> {
+        self.shape.iter().map(|&d| d.max(0) as usize).collect()
+    }
 }
 
 impl fmt::Display for ElementType {
@@ -171,6 +365,7 @@ impl<'a> Tensor<'a> {
     /// # Safety
     ///
     /// The [`ffi::RuneCoralTensor`] can't outlive `self`.
+    #[cfg(feature = "native")]
     pub(crate) unsafe fn as_coral_tensor(&self) -> ffi::RuneCoralTensor {
         ffi::RuneCoralTensor {
             name: std::ptr::null(),
@@ -178,6 +373,23 @@ impl<'a> Tensor<'a> {
             data: self.buffer.as_ptr() as *mut _,
             shape: self.shape.as_ptr(),
             rank: self.shape.len() as ffi::size_t,
+            bytes: self.buffer.len() as ffi::size_t,
+        }
+    }
+
+    /// Create a [`Tensor`] which borrows the data from a
+    /// [`ffi::RuneCoralTensor`].
+    ///
+    /// # Safety
+    ///
+    /// The tensor's `data` and `shape` must be valid for `bytes` bytes and
+    /// `rank` elements respectively (or null if these are zero).
+    #[cfg(feature = "native")]
+    pub(crate) unsafe fn from_rune_coral_tensor(tensor: &'a ffi::RuneCoralTensor) -> Self {
+        Tensor {
+            element_type: ElementType::from(tensor.type_),
+            buffer: slice_from_raw(tensor.data as *const u8, tensor.bytes as usize),
+            shape: Cow::Borrowed(slice_from_raw(tensor.shape, tensor.rank as usize)),
         }
     }
 
@@ -198,6 +410,24 @@ impl<'a> Tensor<'a> {
             shape: Cow::Borrowed(&self.shape),
         }
     }
+
+    /// View this tensor's buffer as a slice of `E`, making sure the element
+    /// type matches.
+    pub fn as_slice<E: TensorElement>(&self) -> Result<&[E], TypeMismatch> {
+        check_element_type::<E>(self.element_type)?;
+        E::from_byte_buffer(self.buffer)
+    }
+}
+
+fn check_element_type<E: TensorElement>(actual: ElementType) -> Result<(), TypeMismatch> {
+    if actual == E::ELEMENT_TYPE {
+        Ok(())
+    } else {
+        Err(TypeMismatch::ElementType {
+            expected: E::ELEMENT_TYPE,
+            actual,
+        })
+    }
 }
 
 /// A mutable reference to a tensor's backing buffer.
@@ -214,6 +444,7 @@ impl<'a> TensorMut<'a> {
     /// # Safety
     ///
     /// The [`ffi::RuneCoralTensor`] can't outlive `self`.
+    #[cfg(feature = "native")]
     pub(crate) unsafe fn as_coral_tensor(&mut self) -> ffi::RuneCoralTensor {
         ffi::RuneCoralTensor {
             name: std::ptr::null(),
@@ -221,6 +452,30 @@ impl<'a> TensorMut<'a> {
             data: self.buffer.as_mut_ptr() as *mut _,
             shape: self.shape.as_ptr(),
             rank: self.shape.len() as ffi::size_t,
+            bytes: self.buffer.len() as ffi::size_t,
+        }
+    }
+
+    /// Create a [`TensorMut`] which borrows the data from a
+    /// [`ffi::RuneCoralTensor`].
+    ///
+    /// # Safety
+    ///
+    /// The tensor's `data` and `shape` must be valid for `bytes` bytes and
+    /// `rank` elements respectively (or null if these are zero), and nothing
+    /// else may access the data while the [`TensorMut`] is alive.
+    #[cfg(feature = "native")]
+    pub(crate) unsafe fn from_rune_coral_tensor(tensor: &'a mut ffi::RuneCoralTensor) -> Self {
+        let buffer = if tensor.bytes > 0 {
+            std::slice::from_raw_parts_mut(tensor.data as *mut u8, tensor.bytes as usize)
+        } else {
+            &mut []
+        };
+
+        TensorMut {
+            element_type: ElementType::from(tensor.type_),
+            buffer,
+            shape: Cow::Borrowed(slice_from_raw(tensor.shape, tensor.rank as usize)),
         }
     }
 
@@ -241,4 +496,90 @@ impl<'a> TensorMut<'a> {
             shape: Cow::Borrowed(&self.shape),
         }
     }
+
+    /// View this tensor's buffer as a slice of `E`, making sure the element
+    /// type matches.
+    pub fn as_slice<E: TensorElement>(&self) -> Result<&[E], TypeMismatch> {
+        check_element_type::<E>(self.element_type)?;
+        E::from_byte_buffer(self.buffer)
+    }
+
+    /// View this tensor's buffer as a mutable slice of `E`, making sure the
+    /// element type matches.
+    pub fn as_mut_slice<E: TensorElement>(&mut self) -> Result<&mut [E], TypeMismatch> {
+        check_element_type::<E>(self.element_type)?;
+        E::from_byte_buffer_mut(self.buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn view_a_tensor_as_its_element_type() {
+        let data = [1.0_f32, 2.0, 3.0];
+        let tensor = Tensor::from_slice(&data, &[3]);
+
+        assert_eq!(tensor.as_slice::<f32>().unwrap(), &data);
+    }
+
+    #[test]
+    fn mutate_a_tensor_through_a_typed_view() {
+        let mut data = [1_i32, 2, 3];
+        let mut tensor = TensorMut::from_slice(&mut data, &[3]);
+
+        tensor.as_mut_slice::<i32>().unwrap()[1] = 42;
+
+        assert_eq!(data, [1, 42, 3]);
+    }
+
+    #[test]
+    fn viewing_with_the_wrong_element_type_fails() {
+        let data = [1.0_f32, 2.0];
+        let tensor = Tensor::from_slice(&data, &[2]);
+
+        assert_eq!(
+            tensor.as_slice::<i32>().unwrap_err(),
+            TypeMismatch::ElementType {
+                expected: ElementType::Int32,
+                actual: ElementType::Float32,
+            }
+        );
+    }
+
+    #[test]
+    fn misaligned_buffers_are_rejected() {
+        let data = [0_f32; 2];
+        let bytes = f32::byte_buffer(&data);
+        let tensor = Tensor {
+            element_type: ElementType::Float32,
+            buffer: &bytes[1..5],
+            shape: Cow::Borrowed(&[1]),
+        };
+
+        assert_eq!(
+            tensor.as_slice::<f32>().unwrap_err(),
+            TypeMismatch::Misaligned { alignment: 4 }
+        );
+    }
+
+    #[test]
+    fn buffers_with_partial_elements_are_rejected() {
+        let data = [0_f32; 2];
+        let bytes = f32::byte_buffer(&data);
+        let tensor = Tensor {
+            element_type: ElementType::Float32,
+            buffer: &bytes[..6],
+            shape: Cow::Borrowed(&[1]),
+        };
+
+        assert_eq!(
+            tensor.as_slice::<f32>().unwrap_err(),
+            TypeMismatch::Length {
+                length: 6,
+                element_size: 4,
+            }
+        );
+    }
 }
@@ -0,0 +1,742 @@
+//! Helpers for regression-testing models against known-good ("golden")
+//! outputs.
+//!
+//! A golden directory contains one `.npy` file per input (`input_0.npy`,
+//! `input_1.npy`, ...) and one per expected output (`output_0.npy`, ...).
+//!
+//! ```rust,no_run
+//! use hotg_runecoral::testing::GoldenTest;
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let model = std::fs::read("model.tflite")?;
+//! GoldenTest::new(&model).run("tests/golden/model")?;
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod npy;
+
+use crate::{
+    mimetype,
+    tensors::{check_tensors, decode_elements},
+    testing::npy::NpyArray,
+    AccelerationBackend, ElementType, Error, InferError, InferenceContext, TensorDescriptor,
+    TensorMismatch, TensorMut,
+};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    convert::TryInto,
+    fmt::{self, Display, Formatter},
+    os::raw::c_int,
+    path::{Path, PathBuf},
+};
+
+/// How far an output element may be from its expected value.
+///
+/// An element is accepted if it is within *any* of the tolerances.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Tolerance {
+    pub absolute: f64,
+    /// The allowed difference, relative to the expected value's magnitude.
+    pub relative: f64,
+    /// The allowed number of representable values between the expected and
+    /// actual values. For integers this is the same as an absolute
+    /// tolerance.
+    pub ulps: u64,
+}
+
+impl Tolerance {
+    pub const EXACT: Tolerance = Tolerance {
+        absolute: 0.0,
+        relative: 0.0,
+        ulps: 0,
+    };
+
+    fn accepts(&self, expected: Element, actual: Element) -> bool {
+        let difference = (expected.value - actual.value).abs();
+
+        expected.value == actual.value
+            || difference <= self.absolute
+            || difference <= self.relative * expected.value.abs()
+            || expected.ordinal.abs_diff_u64(actual.ordinal) <= self.ulps
+    }
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Tolerance::EXACT
+    }
+}
+
+impl Display for Tolerance {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "abs {}, rel {}, {} ulps",
+            self.absolute, self.relative, self.ulps
+        )
+    }
+}
+
+/// The [`Tolerance`] to use for each [`ElementType`].
+///
+/// By default integer outputs must match exactly and floating point outputs
+/// may differ by a few ulps or a small relative error.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Tolerances {
+    overrides: HashMap<ElementType, Tolerance>,
+}
+
+impl Tolerances {
+    /// Require every output to match exactly.
+    pub fn exact() -> Self {
+        let overrides = ALL_ELEMENT_TYPES
+            .iter()
+            .map(|&ty| (ty, Tolerance::EXACT))
+            .collect();
+
+        Tolerances { overrides }
+    }
+
+    /// Use a different [`Tolerance`] for one [`ElementType`].
+    pub fn with(mut self, element_type: ElementType, tolerance: Tolerance) -> Self {
+        self.overrides.insert(element_type, tolerance);
+        self
+    }
+
+    pub fn get(&self, element_type: ElementType) -> Tolerance {
+        if let Some(tolerance) = self.overrides.get(&element_type) {
+            return *tolerance;
+        }
+
+        match element_type {
+            ElementType::Float16 => Tolerance {
+                absolute: 1e-3,
+                relative: 1e-3,
+                ulps: 2,
+            },
+            ElementType::Float32 | ElementType::Complex64 => Tolerance {
+                absolute: 1e-5,
+                relative: 1e-5,
+                ulps: 4,
+            },
+            ElementType::Float64 | ElementType::Complex128 => Tolerance {
+                absolute: 1e-12,
+                relative: 1e-12,
+                ulps: 4,
+            },
+            _ => Tolerance::EXACT,
+        }
+    }
+}
+
+const ALL_ELEMENT_TYPES: &[ElementType] = &[
+    ElementType::Float32,
+    ElementType::Int32,
+    ElementType::UInt8,
+    ElementType::Int64,
+    ElementType::Bool,
+    ElementType::Int16,
+    ElementType::Complex64,
+    ElementType::Int8,
+    ElementType::Float16,
+    ElementType::Float64,
+    ElementType::Complex128,
+];
+
+/// Runs a model against directories of golden inputs and outputs.
+#[derive(Debug, Clone)]
+pub struct GoldenTest<'m> {
+    model: &'m [u8],
+    acceleration_backend: AccelerationBackend,
+    tolerances: Tolerances,
+}
+
+impl<'m> GoldenTest<'m> {
+    pub fn new(model: &'m [u8]) -> Self {
+        GoldenTest {
+            model,
+            acceleration_backend: AccelerationBackend::NONE,
+            tolerances: Tolerances::default(),
+        }
+    }
+
+    pub fn acceleration_backend(mut self, acceleration_backend: AccelerationBackend) -> Self {
+        self.acceleration_backend = acceleration_backend;
+        self
+    }
+
+    pub fn tolerances(mut self, tolerances: Tolerances) -> Self {
+        self.tolerances = tolerances;
+        self
+    }
+
+    /// Run the model on the inputs in `dir` and compare the results with the
+    /// expected outputs.
+    pub fn run(&self, dir: impl AsRef<Path>) -> Result<(), GoldenError> {
+        let dir = dir.as_ref();
+        let inputs = read_arrays(dir, "input")?;
+        let expected = read_arrays(dir, "output")?;
+
+        if inputs.is_empty() || expected.is_empty() {
+            return Err(GoldenError::MissingFiles {
+                dir: dir.to_path_buf(),
+            });
+        }
+
+        let mut ctx = InferenceContext::builder()
+            .acceleration_backend(self.acceleration_backend)
+            .build(mimetype(), self.model)?;
+
+        check_arrays(Direction::Input, &inputs, ctx.inputs())?;
+        check_arrays(Direction::Output, &expected, ctx.outputs())?;
+
+        // The output buffers are sized from the model rather than the golden
+        // files so the interpreter can never write past the end of them.
+        let descriptors: Vec<_> = ctx
+            .outputs()
+            .map(|d| {
+                (
+                    d.name.to_string_lossy().into_owned(),
+                    d.element_type,
+                    d.shape.into_owned(),
+                )
+            })
+            .collect();
+        let mut actual: Vec<_> = ctx.outputs().map(|d| vec![0_u8; d.byte_len()]).collect();
+
+        let input_tensors: Vec<_> = inputs.iter().map(|a| a.tensor()).collect();
+        let mut output_tensors: Vec<_> = actual
+            .iter_mut()
+            .zip(&descriptors)
+            .map(|(buffer, (_, element_type, shape))| TensorMut {
+                element_type: *element_type,
+                buffer,
+                shape: Cow::Borrowed(shape),
+            })
+            .collect();
+
+        ctx.infer(&input_tensors, &mut output_tensors)?;
+        drop(output_tensors);
+
+        let outputs: Vec<_> = expected
+            .iter()
+            .zip(&actual)
+            .zip(descriptors)
+            .enumerate()
+            .filter_map(|(index, ((expected, actual), (name, element_type, _)))| {
+                let tolerance = self.tolerances.get(element_type);
+                compare(index, name, element_type, tolerance, &expected.data, actual)
+            })
+            .collect();
+
+        if outputs.is_empty() {
+            Ok(())
+        } else {
+            Err(GoldenError::Mismatch(Report { outputs }))
+        }
+    }
+}
+
+/// Make sure the golden arrays for the model's inputs or outputs have the
+/// same element types, shapes and sizes as the model's tensors.
+///
+/// Dynamic (negative) dimensions in the model match any size.
+fn check_arrays<'a>(
+    direction: Direction,
+    arrays: &[NpyArray],
+    descriptors: impl Iterator<Item = TensorDescriptor<'a>>,
+) -> Result<(), GoldenError> {
+    let descriptors: Vec<_> = descriptors.collect();
+
+    for (index, (array, descriptor)) in arrays.iter().zip(&descriptors).enumerate() {
+        if !same_shape(&array.shape, &descriptor.shape) {
+            return Err(GoldenError::Shape {
+                direction,
+                index,
+                expected: array.shape.clone(),
+                actual: descriptor.shape.to_vec(),
+            });
+        }
+    }
+
+    // The shapes match, so dynamic dimensions can take their size from the
+    // golden arrays
+    let expected = descriptors
+        .iter()
+        .enumerate()
+        .map(|(i, d)| match arrays.get(i) {
+            Some(array) => (
+                d.element_type,
+                array.shape.iter().product::<usize>() * d.element_type.size(),
+            ),
+            None => (d.element_type, d.byte_len()),
+        });
+
+    check_tensors(
+        expected,
+        arrays.iter().map(|a| (a.element_type, a.data.len())),
+    )
+    .map_err(|error| GoldenError::Tensors { direction, error })
+}
+
+/// Does a golden array have the same shape as a model's tensor?
+fn same_shape(array: &[usize], tensor: &[c_int]) -> bool {
+    array.len() == tensor.len()
+        && array
+            .iter()
+            .zip(tensor)
+            .all(|(&a, &t)| t < 0 || a == t as usize)
+}
+
+/// Read `{prefix}_0.npy`, `{prefix}_1.npy`, ... until a file is missing.
+fn read_arrays(dir: &Path, prefix: &str) -> Result<Vec<NpyArray>, GoldenError> {
+    let mut arrays = Vec::new();
+
+    loop {
+        let path = dir.join(format!("{}_{}.npy", prefix, arrays.len()));
+        if !path.exists() {
+            return Ok(arrays);
+        }
+
+        let array = NpyArray::read(&path).map_err(|error| GoldenError::Npy { path, error })?;
+        arrays.push(array);
+    }
+}
+
+/// Errors from [`GoldenTest::run()`].
+#[derive(Debug, thiserror::Error)]
+pub enum GoldenError {
+    #[error("\"{}\" doesn't contain any input_N.npy or output_N.npy files", dir.display())]
+    MissingFiles { dir: PathBuf },
+    #[error("Unable to read \"{}\"", path.display())]
+    Npy {
+        path: PathBuf,
+        #[source]
+        error: npy::NpyError,
+    },
+    #[error("Unable to create the inference context")]
+    Context(#[from] Error),
+    #[error("Inference failed")]
+    Infer(#[from] InferError),
+    #[error("The golden {direction}s don't match the model: {error}")]
+    Tensors {
+        direction: Direction,
+        error: TensorMismatch,
+    },
+    #[error("Expected {direction} {index} to have the shape {expected:?}, but it has {actual:?}")]
+    Shape {
+        direction: Direction,
+        index: usize,
+        expected: Vec<usize>,
+        actual: Vec<c_int>,
+    },
+    #[error("{}", _0)]
+    Mismatch(Report),
+}
+
+/// Whether a golden file is one of a model's inputs or its outputs.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Direction {
+    Input,
+    Output,
+}
+
+impl Display for Direction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Direction::Input => f.write_str("input"),
+            Direction::Output => f.write_str("output"),
+        }
+    }
+}
+
+/// A description of every output which didn't match its golden value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub outputs: Vec<OutputDiff>,
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (i, output) in self.outputs.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", output)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The elements of one output which didn't match their golden values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputDiff {
+    pub index: usize,
+    pub name: String,
+    pub element_type: ElementType,
+    pub tolerance: Tolerance,
+    /// The total number of elements in the output.
+    pub len: usize,
+    pub mismatches: Vec<Mismatch>,
+}
+
+/// The maximum number of mismatched elements to show for each output.
+const MAX_MISMATCHES_SHOWN: usize = 10;
+
+impl Display for OutputDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "output {} (\"{}\", {}): {} of {} elements outside tolerance ({})",
+            self.index,
+            self.name,
+            self.element_type,
+            self.mismatches.len(),
+            self.len,
+            self.tolerance
+        )?;
+
+        for mismatch in self.mismatches.iter().take(MAX_MISMATCHES_SHOWN) {
+            writeln!(
+                f,
+                "    [{}]: expected {}, actual {} (abs diff {:e})",
+                mismatch.index,
+                mismatch.expected,
+                mismatch.actual,
+                (mismatch.expected - mismatch.actual).abs()
+            )?;
+        }
+
+        if self.mismatches.len() > MAX_MISMATCHES_SHOWN {
+            writeln!(
+                f,
+                "    ... and {} more",
+                self.mismatches.len() - MAX_MISMATCHES_SHOWN
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// An element which wasn't within tolerance of its golden value.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Mismatch {
+    /// The element's index in the flattened output.
+    pub index: usize,
+    pub expected: f64,
+    pub actual: f64,
+}
+
+fn compare(
+    index: usize,
+    name: String,
+    element_type: ElementType,
+    tolerance: Tolerance,
+    expected: &[u8],
+    actual: &[u8],
+) -> Option<OutputDiff> {
+    let expected = elements(element_type, expected);
+    let actual = elements(element_type, actual);
+
+    let mismatches: Vec<_> = expected
+        .iter()
+        .zip(&actual)
+        .enumerate()
+        .filter(|(_, (&e, &a))| !tolerance.accepts(e, a))
+        .map(|(index, (e, a))| Mismatch {
+            index,
+            expected: e.value,
+            actual: a.value,
+        })
+        .collect();
+
+    if mismatches.is_empty() {
+        None
+    } else {
+        Some(OutputDiff {
+            index,
+            name,
+            element_type,
+            tolerance,
+            len: expected.len(),
+            mismatches,
+        })
+    }
+}
+
+/// A tensor element, normalized so elements of any type can be compared.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Element {
+    value: f64,
+    /// The element's position in the ordered sequence of values its type can
+    /// represent, used for calculating ulps.
+    ordinal: Ordinal,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Ordinal(i128);
+
+impl Ordinal {
+    /// Map a float's bits to a value where adjacent floats are adjacent
+    /// integers.
+    fn from_float_bits(bits: u64, sign_bit: u64) -> Self {
+        if bits & sign_bit != 0 {
+            Ordinal(-((bits & !sign_bit) as i128))
+        } else {
+            Ordinal(bits as i128)
+        }
+    }
+
+    fn abs_diff_u64(self, other: Ordinal) -> u64 {
+        (self.0 - other.0)
+            .unsigned_abs()
+            .try_into()
+            .unwrap_or(u64::MAX)
+    }
+}
+
+/// Decode a tensor's bytes, treating complex numbers as pairs of floats.
+///
+/// TensorFlow Lite only runs on little-endian machines, so the golden files'
+/// little-endian data has the same layout as the model's outputs.
+fn elements(element_type: ElementType, bytes: &[u8]) -> Vec<Element> {
+    macro_rules! decode {
+        ($ty:ty, |$v:ident| $element:expr) => {
+            decode_elements!(bytes, $ty, |$v| $element)
+        };
+    }
+
+    fn int(value: i128) -> Element {
+        Element {
+            value: value as f64,
+            ordinal: Ordinal(value),
+        }
+    }
+
+    match element_type {
+        ElementType::Bool | ElementType::UInt8 => decode!(u8, |v| int(v.into())),
+        ElementType::Int8 => decode!(i8, |v| int(v.into())),
+        ElementType::Int16 => decode!(i16, |v| int(v.into())),
+        ElementType::Int32 => decode!(i32, |v| int(v.into())),
+        ElementType::Int64 => decode!(i64, |v| int(v.into())),
+        ElementType::Float16 => decode!(u16, |v| Element {
+            value: f16_to_f64(v),
+            ordinal: Ordinal::from_float_bits(v.into(), 1 << 15),
+        }),
+        ElementType::Float32 | ElementType::Complex64 => decode!(f32, |v| Element {
+            value: v.into(),
+            ordinal: Ordinal::from_float_bits(v.to_bits().into(), 1 << 31),
+        }),
+        ElementType::Float64 | ElementType::Complex128 => decode!(f64, |v| Element {
+            value: v,
+            ordinal: Ordinal::from_float_bits(v.to_bits(), 1 << 63),
+        }),
+        ElementType::NoType | ElementType::String => decode!(u8, |v| int(v.into())),
+    }
+}
+
+fn f16_to_f64(bits: u16) -> f64 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let mantissa = (bits & 0x3ff) as f64;
+
+    match exponent {
+        0 => sign * mantissa * 2_f64.powi(-24),
+        0x1f if mantissa == 0.0 => sign * f64::INFINITY,
+        0x1f => f64::NAN,
+        _ => sign * (1.0 + mantissa / 1024.0) * 2_f64.powi(exponent - 15),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CStr;
+
+    fn f32_bytes(values: &[f32]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn identical_outputs_match_exactly() {
+        let bytes = f32_bytes(&[1.0, -2.5, 0.0]);
+
+        let diff = compare(
+            0,
+            "out".to_string(),
+            ElementType::Float32,
+            Tolerance::EXACT,
+            &bytes,
+            &bytes,
+        );
+
+        assert_eq!(diff, None);
+    }
+
+    #[test]
+    fn ulp_tolerance_accepts_adjacent_floats() {
+        let expected = 1.0_f32;
+        let next = f32::from_bits(expected.to_bits() + 1);
+        let tolerance = Tolerance {
+            ulps: 1,
+            ..Tolerance::EXACT
+        };
+
+        let diff = compare(
+            0,
+            "out".to_string(),
+            ElementType::Float32,
+            tolerance,
+            &f32_bytes(&[expected, -0.0]),
+            &f32_bytes(&[next, 0.0]),
+        );
+
+        assert_eq!(diff, None);
+    }
+
+    #[test]
+    fn report_elements_outside_tolerance() {
+        let tolerance = Tolerance {
+            absolute: 0.1,
+            ..Tolerance::EXACT
+        };
+
+        let diff = compare(
+            2,
+            "Identity".to_string(),
+            ElementType::Float32,
+            tolerance,
+            &f32_bytes(&[1.0, 2.0, 3.0]),
+            &f32_bytes(&[1.05, 2.5, 3.0]),
+        )
+        .unwrap();
+
+        assert_eq!(
+            diff.mismatches,
+            vec![Mismatch {
+                index: 1,
+                expected: 2.0,
+                actual: 2.5
+            }]
+        );
+        let report = diff.to_string();
+        assert!(
+            report.starts_with("output 2 (\"Identity\", f32): 1 of 3 elements outside tolerance")
+        );
+        assert!(report.contains("[1]: expected 2, actual 2.5"));
+    }
+
+    #[test]
+    fn integers_use_exact_tolerances_by_default() {
+        let tolerances = Tolerances::default();
+
+        let diff = compare(
+            0,
+            "out".to_string(),
+            ElementType::UInt8,
+            tolerances.get(ElementType::UInt8),
+            &[1, 2, 3],
+            &[1, 2, 4],
+        );
+
+        assert_eq!(diff.unwrap().mismatches.len(), 1);
+        assert_eq!(
+            Tolerances::exact().get(ElementType::Float32),
+            Tolerance::EXACT
+        );
+    }
+
+    fn descriptor(element_type: ElementType, shape: &'static [c_int]) -> TensorDescriptor<'static> {
+        TensorDescriptor {
+            name: CStr::from_bytes_with_nul(b"input\0").unwrap(),
+            element_type,
+            shape: Cow::Borrowed(shape),
+        }
+    }
+
+    #[test]
+    fn golden_inputs_must_match_the_model() {
+        let array = NpyArray::new(ElementType::Float32, vec![1, 2], f32_bytes(&[1.0, 2.0]));
+
+        let inputs = std::slice::from_ref(&array);
+
+        check_arrays(
+            Direction::Input,
+            inputs,
+            vec![descriptor(ElementType::Float32, &[1, 2])].into_iter(),
+        )
+        .unwrap();
+
+        let err = check_arrays(
+            Direction::Input,
+            inputs,
+            vec![descriptor(ElementType::Float32, &[1, 4])].into_iter(),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            GoldenError::Shape { direction: Direction::Input, index: 0, ref expected, ref actual }
+                if expected == &[1, 2] && actual == &[1, 4]
+        ));
+
+        let err = check_arrays(
+            Direction::Input,
+            inputs,
+            vec![descriptor(ElementType::UInt8, &[1, 2])].into_iter(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The golden inputs don't match the model: Expected tensor 0 to be 2 bytes of u8, but it is 8 bytes of f32"
+        );
+    }
+
+    #[test]
+    fn dynamic_dimensions_match_any_size() {
+        let array = NpyArray::new(ElementType::Float32, vec![3, 2], f32_bytes(&[0.0; 6]));
+
+        check_arrays(
+            Direction::Input,
+            std::slice::from_ref(&array),
+            vec![descriptor(ElementType::Float32, &[-1, 2])].into_iter(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn truncated_golden_outputs_are_rejected() {
+        let truncated = NpyArray::new(ElementType::Float32, vec![2], f32_bytes(&[1.0]));
+
+        let err = check_arrays(
+            Direction::Output,
+            std::slice::from_ref(&truncated),
+            vec![descriptor(ElementType::Float32, &[2])].into_iter(),
+        )
+        .unwrap_err();
+
+        assert!(matches!(
+            err,
+            GoldenError::Tensors {
+                direction: Direction::Output,
+                error: TensorMismatch::Tensor {
+                    index: 0,
+                    expected_bytes: 8,
+                    actual_bytes: 4,
+                    ..
+                },
+            }
+        ));
+    }
+
+    #[test]
+    fn decode_half_precision_floats() {
+        assert_eq!(f16_to_f64(0x3c00), 1.0);
+        assert_eq!(f16_to_f64(0xc000), -2.0);
+        assert_eq!(f16_to_f64(0x0001), 2_f64.powi(-24));
+        assert_eq!(f16_to_f64(0x7c00), f64::INFINITY);
+    }
+}
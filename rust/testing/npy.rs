@@ -0,0 +1,278 @@
+//! Reading and writing tensors in NumPy's [`.npy` format][npy].
+//!
+//! Only little-endian, C-ordered arrays of the element types supported by
+//! TensorFlow Lite are supported.
+//!
+//! [npy]: https://numpy.org/doc/stable/reference/generated/numpy.lib.format.html
+
+use crate::{ElementType, Tensor};
+use std::{borrow::Cow, convert::TryInto, os::raw::c_int, path::Path};
+
+const MAGIC: &[u8] = b"\x93NUMPY";
+
+/// An array loaded from a `.npy` file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NpyArray {
+    pub element_type: ElementType,
+    pub shape: Vec<usize>,
+    /// The array's elements as little-endian bytes.
+    pub data: Vec<u8>,
+}
+
+impl NpyArray {
+    pub fn new(element_type: ElementType, shape: Vec<usize>, data: Vec<u8>) -> Self {
+        NpyArray {
+            element_type,
+            shape,
+            data,
+        }
+    }
+
+    /// Read a `.npy` file from disk.
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, NpyError> {
+        let bytes = std::fs::read(path)?;
+        NpyArray::parse(&bytes)
+    }
+
+    /// Parse the contents of a `.npy` file.
+    pub fn parse(bytes: &[u8]) -> Result<Self, NpyError> {
+        if !bytes.starts_with(MAGIC) {
+            return Err(NpyError::BadMagic);
+        }
+
+        let (header_len, header_start) = match bytes.get(MAGIC.len()) {
+            Some(1) => (read_u16(bytes, 8)? as usize, 10),
+            Some(2) | Some(3) => (read_u32(bytes, 8)? as usize, 12),
+            Some(&version) => return Err(NpyError::UnsupportedVersion(version)),
+            None => return Err(NpyError::Truncated),
+        };
+
+        let header = bytes
+            .get(header_start..header_start + header_len)
+            .ok_or(NpyError::Truncated)?;
+        let header = std::str::from_utf8(header).map_err(|_| NpyError::BadHeader)?;
+
+        let descr = header_value(header, "descr")?;
+        let descr = descr.trim_matches(|c| c == '\'' || c == '"');
+        let element_type = element_type(descr)?;
+
+        if header_value(header, "fortran_order")? != "False" {
+            return Err(NpyError::FortranOrder);
+        }
+
+        let shape = parse_shape(header_value(header, "shape")?)?;
+
+        let data = &bytes[header_start + header_len..];
+        let expected = shape.iter().product::<usize>() * element_type.size();
+        if data.len() != expected {
+            return Err(NpyError::Length {
+                expected,
+                actual: data.len(),
+            });
+        }
+
+        Ok(NpyArray::new(element_type, shape, data.to_vec()))
+    }
+
+    /// Serialize this array using the `.npy` format.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let shape = match self.shape.as_slice() {
+            [dim] => format!("({},)", dim),
+            dims => format!(
+                "({})",
+                dims.iter()
+                    .map(|d| d.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        };
+        let mut header = format!(
+            "{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}",
+            descr(self.element_type),
+            shape
+        );
+
+        // The header is padded with spaces and terminated by a newline so the
+        // data is 64-byte aligned
+        let unpadded = MAGIC.len() + 4 + header.len() + 1;
+        header.push_str(&" ".repeat((64 - unpadded % 64) % 64));
+        header.push('\n');
+
+        let mut bytes = Vec::with_capacity(MAGIC.len() + 4 + header.len() + self.data.len());
+        bytes.extend(MAGIC);
+        bytes.extend([1, 0]);
+        bytes.extend((header.len() as u16).to_le_bytes());
+        bytes.extend(header.as_bytes());
+        bytes.extend(&self.data);
+
+        bytes
+    }
+
+    /// View this array as a [`Tensor`] which can be passed to
+    /// [`crate::InferenceContext::infer()`].
+    pub fn tensor(&self) -> Tensor<'_> {
+        Tensor {
+            element_type: self.element_type,
+            buffer: &self.data,
+            shape: Cow::Owned(self.shape.iter().map(|&d| d as c_int).collect()),
+        }
+    }
+}
+
+/// Errors that may occur while reading a `.npy` file.
+#[derive(Debug, thiserror::Error)]
+pub enum NpyError {
+    #[error("Unable to read the file")]
+    Io(#[from] std::io::Error),
+    #[error("Not a .npy file")]
+    BadMagic,
+    #[error("Version {} .npy files aren't supported", _0)]
+    UnsupportedVersion(u8),
+    #[error("The file is truncated")]
+    Truncated,
+    #[error("Unable to parse the header")]
+    BadHeader,
+    #[error("Arrays with dtype \"{}\" aren't supported", _0)]
+    UnsupportedDtype(String),
+    #[error("Fortran-ordered arrays aren't supported")]
+    FortranOrder,
+    #[error("Expected {expected} bytes of data but found {actual}")]
+    Length { expected: usize, actual: usize },
+}
+
+fn element_type(descr: &str) -> Result<ElementType, NpyError> {
+    let ty = match descr {
+        "|b1" => ElementType::Bool,
+        "|u1" | "<u1" => ElementType::UInt8,
+        "|i1" | "<i1" => ElementType::Int8,
+        "<i2" => ElementType::Int16,
+        "<i4" => ElementType::Int32,
+        "<i8" => ElementType::Int64,
+        "<f2" => ElementType::Float16,
+        "<f4" => ElementType::Float32,
+        "<f8" => ElementType::Float64,
+        "<c8" => ElementType::Complex64,
+        "<c16" => ElementType::Complex128,
+        other => return Err(NpyError::UnsupportedDtype(other.to_string())),
+    };
+
+    Ok(ty)
+}
+
+fn descr(element_type: ElementType) -> &'static str {
+    match element_type {
+        ElementType::Bool => "|b1",
+        ElementType::UInt8 => "|u1",
+        ElementType::Int8 => "|i1",
+        ElementType::Int16 => "<i2",
+        ElementType::Int32 => "<i4",
+        ElementType::Int64 => "<i8",
+        ElementType::Float16 => "<f2",
+        ElementType::Float32 => "<f4",
+        ElementType::Float64 => "<f8",
+        ElementType::Complex64 => "<c8",
+        ElementType::Complex128 => "<c16",
+        ElementType::NoType | ElementType::String => "|V0",
+    }
+}
+
+/// Find the (unparsed) value for a key in the header's Python dict literal.
+fn header_value<'h>(header: &'h str, key: &str) -> Result<&'h str, NpyError> {
+    let start = header
+        .find(&format!("'{}'", key))
+        .ok_or(NpyError::BadHeader)?;
+    let rest = header[start + key.len() + 2..].trim_start();
+    let rest = rest
+        .strip_prefix(':')
+        .ok_or(NpyError::BadHeader)?
+        .trim_start();
+
+    // Tuples contain commas, so they need to be matched up to the closing
+    // parenthesis
+    let end = if rest.starts_with('(') {
+        rest.find(')').map(|i| i + 1)
+    } else {
+        rest.find(&[',', '}'][..])
+    };
+
+    end.map(|end| rest[..end].trim()).ok_or(NpyError::BadHeader)
+}
+
+fn parse_shape(shape: &str) -> Result<Vec<usize>, NpyError> {
+    shape
+        .strip_prefix('(')
+        .and_then(|s| s.strip_suffix(')'))
+        .ok_or(NpyError::BadHeader)?
+        .split(',')
+        .map(str::trim)
+        .filter(|dim| !dim.is_empty())
+        .map(|dim| dim.parse().map_err(|_| NpyError::BadHeader))
+        .collect()
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Result<u16, NpyError> {
+    let bytes = bytes.get(offset..offset + 2).ok_or(NpyError::Truncated)?;
+    Ok(u16::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, NpyError> {
+    let bytes = bytes.get(offset..offset + 4).ok_or(NpyError::Truncated)?;
+    Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_an_array() {
+        let data: Vec<u8> = [1.0_f32, 2.0, 3.0, 4.0, 5.0, 6.0]
+            .iter()
+            .flat_map(|f| f.to_le_bytes())
+            .collect();
+        let array = NpyArray::new(ElementType::Float32, vec![2, 3], data);
+
+        let bytes = array.to_bytes();
+
+        assert_eq!((bytes.len() - 24) % 64, 0);
+        assert_eq!(NpyArray::parse(&bytes).unwrap(), array);
+    }
+
+    #[test]
+    fn parse_a_file_written_by_numpy() {
+        // np.save(f, np.array([1, 2, 3], dtype=np.int16))
+        let mut bytes = b"\x93NUMPY\x01\x00v\x00".to_vec();
+        let header = "{'descr': '<i2', 'fortran_order': False, 'shape': (3,), }";
+        bytes.extend(header.as_bytes());
+        bytes.extend(vec![b' '; 118 - header.len() - 1]);
+        bytes.push(b'\n');
+        bytes.extend([1, 0, 2, 0, 3, 0]);
+
+        let array = NpyArray::parse(&bytes).unwrap();
+
+        assert_eq!(array.element_type, ElementType::Int16);
+        assert_eq!(array.shape, vec![3]);
+        assert_eq!(array.data, vec![1, 0, 2, 0, 3, 0]);
+    }
+
+    #[test]
+    fn scalars_have_an_empty_shape() {
+        let array = NpyArray::new(ElementType::UInt8, Vec::new(), vec![42]);
+
+        let parsed = NpyArray::parse(&array.to_bytes()).unwrap();
+
+        assert!(parsed.shape.is_empty());
+        assert_eq!(parsed.data, vec![42]);
+    }
+
+    #[test]
+    fn reject_fortran_ordered_arrays() {
+        let mut bytes = b"\x93NUMPY\x01\x00\x39\x00".to_vec();
+        bytes.extend(b"{'descr': '<f4', 'fortran_order': True, 'shape': (0,), }\n");
+
+        assert!(matches!(
+            NpyArray::parse(&bytes),
+            Err(NpyError::FortranOrder)
+        ));
+    }
+}
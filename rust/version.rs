@@ -0,0 +1,116 @@
+use crate::{ffi, AccelerationBackend, LoadError};
+use std::{ffi::CStr, os::raw::c_char, sync::Mutex};
+
+/// The `RUNECORAL_ABI_VERSION` from the `runecoral.h` this crate was written
+/// against.
+///
+/// This is pinned rather than taken from the generated bindings, because
+/// those come from whichever `runecoral.h` the build script found and would
+/// always agree with it.
+pub const ABI_VERSION: u32 = 3;
+
+/// `sizeof(RuneCoralTensor)`, which is an element type padded to pointer
+/// alignment followed by three pointers and two `size_t`s.
+const TENSOR_SIZE: usize = 6 * std::mem::size_of::<usize>();
+
+static ABI_CHECK: Mutex<Option<Result<(), LoadError>>> = Mutex::new(None);
+
+/// Versions of `librunecoral` and the libraries it was built with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Version {
+    pub librunecoral: String,
+    /// The `RUNECORAL_ABI_VERSION` `librunecoral` was built with.
+    pub abi_version: u32,
+    pub tflite: String,
+    /// The version of each delegate `librunecoral` was built with.
+    pub delegates: Vec<(AccelerationBackend, String)>,
+}
+
+/// Get the versions of `librunecoral`, TensorFlow Lite, and any delegates.
+///
+/// This fails if `librunecoral` can't be loaded.
+pub fn version() -> Result<Version, LoadError> {
+    #[cfg(feature = "dynamic")]
+    crate::library::check_available()?;
+
+    let backends = [
+        AccelerationBackend::EDGETPU,
+        AccelerationBackend::GPU,
+        AccelerationBackend::XNNPACK,
+    ];
+
+    unsafe {
+        let delegates = backends
+            .iter()
+            .filter_map(|&backend| {
+                let version = ffi::runecoral_delegate_version(backend.bits());
+                string(version).map(|v| (backend, v))
+            })
+            .collect();
+
+        Ok(Version {
+            librunecoral: string(ffi::runecoral_version()).unwrap_or_default(),
+            abi_version: ffi::runecoral_abi_version() as u32,
+            tflite: string(ffi::runecoral_tflite_version()).unwrap_or_default(),
+            delegates,
+        })
+    }
+}
+
+/// Make sure `librunecoral` was built from the same `runecoral.h` as this
+/// crate, because passing structs with a different layout across the FFI
+/// boundary would corrupt memory.
+///
+/// The library can't change once it is loaded, so the result is cached after
+/// the first call.
+pub(crate) fn check_abi() -> Result<(), LoadError> {
+    let mut result = ABI_CHECK.lock().unwrap();
+
+    result.get_or_insert_with(compare_abi).clone()
+}
+
+fn compare_abi() -> Result<(), LoadError> {
+    let (abi_version, tensor_size) =
+        unsafe { (ffi::runecoral_abi_version(), ffi::runecoral_sizeof_tensor()) };
+
+    if abi_version as u32 == ABI_VERSION && tensor_size as usize == TENSOR_SIZE {
+        return Ok(());
+    }
+
+    Err(LoadError::IncompatibleLibrary {
+        library_version: unsafe { string(ffi::runecoral_version()) }.unwrap_or_default(),
+        expected_abi: ABI_VERSION,
+        actual_abi: abi_version as u32,
+        expected_tensor_size: TENSOR_SIZE,
+        actual_tensor_size: tensor_size as usize,
+    })
+}
+
+/// # Safety
+///
+/// The pointer must be null or point to a valid C string.
+unsafe fn string(s: *const c_char) -> Option<String> {
+    if s.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(s).to_string_lossy().into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_pinned_abi_matches_the_header() {
+        let header = include_str!("../runecoral/runecoral.h");
+        let version = header
+            .lines()
+            .find_map(|line| line.strip_prefix("#define RUNECORAL_ABI_VERSION "))
+            .expect("runecoral.h should define RUNECORAL_ABI_VERSION");
+
+        assert_eq!(version.trim().parse::<u32>().unwrap(), ABI_VERSION);
+        assert_eq!(ffi::RUNECORAL_ABI_VERSION, ABI_VERSION);
+        assert_eq!(std::mem::size_of::<ffi::RuneCoralTensor>(), TENSOR_SIZE);
+    }
+}
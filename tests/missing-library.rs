@@ -0,0 +1,39 @@
+//! Everything which calls into `librunecoral` should fail gracefully when it
+//! can't be loaded. This lives in its own test binary so pointing
+//! `$RUNECORAL_LIBRARY` somewhere else doesn't affect the other tests.
+
+#![cfg(feature = "dynamic")]
+
+use hotg_runecoral::{
+    edgetpu_devices, library, mimetype, resolve_mimetype, supported_mimetypes, version,
+    AccelerationBackend, InferenceContext, LoadError, Model, OpResolver, VerifyError,
+};
+
+#[test]
+fn nothing_panics_without_librunecoral() {
+    std::env::set_var(library::LIBRARY_ENV_VAR, "/path/to/nowhere/librunecoral.so");
+    let model = include_bytes!("sinemodel.tflite");
+
+    assert!(!library::is_available());
+    assert!(matches!(
+        Model::verify(model),
+        Err(VerifyError::LibraryUnavailable(_))
+    ));
+    assert!(supported_mimetypes().is_empty());
+    assert_eq!(resolve_mimetype(Some(mimetype()), model), None);
+    assert!(matches!(version(), Err(LoadError::LibraryUnavailable(_))));
+    assert!(matches!(
+        OpResolver::new(),
+        Err(LoadError::LibraryUnavailable(_))
+    ));
+    assert!(matches!(
+        Model::load(model),
+        Err(LoadError::LibraryUnavailable(_))
+    ));
+    assert!(InferenceContext::builder().build(mimetype(), model).is_err());
+    assert_eq!(
+        AccelerationBackend::currently_available(),
+        AccelerationBackend::NONE
+    );
+    assert!(edgetpu_devices().is_empty());
+}
@@ -1,6 +1,12 @@
+#![cfg(feature = "native")]
+
 use hotg_runecoral::{
-    mimetype, AccelerationBackend, ElementType, Error, InferenceContext, LoadError, Tensor,
-    TensorDescriptor, TensorMut,
+    edgetpu_devices, mimetype, resolve_mimetype, supported_mimetypes, version,
+    AccelerationBackend, Backend, BackendRegistry, Benchmark, Capabilities, Comparison,
+    ContextState, EdgeTpuOptions, EdgeTpuSelector, ElementType, Error, InferError,
+    InferenceContext, LoadError, MetadataError, Model, OpResolver, Placement, RestoreError,
+    Target, Tensor, TensorDescriptor, TensorMismatch, TensorMut, VerifyError, ABI_VERSION,
+    TFLITE_METADATA,
 };
 use std::borrow::Cow;
 use std::ffi::CStr;
@@ -14,6 +20,45 @@ fn create_inference_context_with_invalid_model() {
     assert_eq!(result.unwrap_err(), Error::Load(LoadError::InternalError));
 }
 
+#[test]
+fn create_inference_context_with_an_unsupported_mimetype() {
+    let model = include_bytes!("sinemodel.tflite");
+
+    let result =
+        InferenceContext::create_context("application/onnx", model, AccelerationBackend::NONE);
+
+    assert_eq!(
+        result.unwrap_err(),
+        Error::Load(LoadError::UnsupportedFormat {
+            mimetype: "application/onnx".to_string(),
+            supported: vec![mimetype().to_string()],
+        })
+    );
+}
+
+#[test]
+fn create_inference_context_with_an_alias_or_no_mimetype() {
+    let model = include_bytes!("sinemodel.tflite");
+
+    for mimetype in &["application/x-tflite", "Application/TFLite-Model", ""] {
+        let ctx =
+            InferenceContext::create_context(mimetype, model, AccelerationBackend::NONE).unwrap();
+        assert_eq!(ctx.opcount(), 3);
+    }
+}
+
+#[test]
+fn resolve_supported_mimetypes() {
+    let model = include_bytes!("sinemodel.tflite");
+
+    assert_eq!(supported_mimetypes(), [mimetype()]);
+    assert_eq!(mimetype(), "application/tflite-model");
+    assert_eq!(resolve_mimetype(Some("application/vnd.tflite"), &[]), Some(mimetype()));
+    assert_eq!(resolve_mimetype(None, model), Some(mimetype()));
+    assert_eq!(resolve_mimetype(None, b"this is not a valid model"), None);
+    assert_eq!(resolve_mimetype(Some("application/tflite-context"), model), None);
+}
+
 #[test]
 fn create_inference_context() {
     let model = include_bytes!("sinemodel.tflite");
@@ -67,6 +112,119 @@ fn round(n: f32) -> f32 {
     (n * 10000.0).round() / 10000.0
 }
 
+#[test]
+fn inference_rejects_incorrectly_sized_tensors() {
+    let model = include_bytes!("sinemodel.tflite");
+    let mut ctx =
+        InferenceContext::create_context(mimetype(), model, AccelerationBackend::NONE).unwrap();
+
+    let input = [0.5_f32, 0.5];
+    let mut output = [0_f32];
+    let err = ctx
+        .infer(
+            &[Tensor::from_slice(&input, &[2])],
+            &mut [TensorMut::from_slice(&mut output, &[1])],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err,
+        InferError::Inputs(TensorMismatch::Tensor {
+            index: 0,
+            expected: ElementType::Float32,
+            expected_bytes: 4,
+            actual: ElementType::Float32,
+            actual_bytes: 8,
+        })
+    );
+
+    let input = [0.5_f32];
+    let mut output: [f32; 0] = [];
+    let err = ctx
+        .infer(
+            &[Tensor::from_slice(&input, &[1])],
+            &mut [TensorMut::from_slice(&mut output, &[0])],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err,
+        InferError::Outputs(TensorMismatch::Tensor {
+            index: 0,
+            expected: ElementType::Float32,
+            expected_bytes: 4,
+            actual: ElementType::Float32,
+            actual_bytes: 0,
+        })
+    );
+}
+
+#[test]
+fn run_the_sine_model_through_the_backend_registry() {
+    let model = include_bytes!("sinemodel.tflite");
+    let registry = BackendRegistry::default();
+    assert_eq!(registry.mimetypes().collect::<Vec<_>>(), [mimetype()]);
+
+    let mut backend = registry
+        .load(mimetype(), model, AccelerationBackend::NONE)
+        .unwrap();
+
+    assert_eq!(backend.inputs()[0].name.to_str().unwrap(), "dense_2_input");
+    assert_eq!(
+        backend.capabilities(),
+        Capabilities {
+            acceleration_backend: AccelerationBackend::NONE,
+            profiling: true,
+            state: true,
+        }
+    );
+    let input = [0.5_f32];
+    let mut output = [0_f32];
+    backend
+        .infer(
+            &[Tensor::from_slice(&input, &[1])],
+            &mut [TensorMut::from_slice(&mut output, &[1])],
+        )
+        .unwrap();
+    assert_eq!(round(output[0]), round(0.4540305));
+}
+
+#[test]
+fn load_an_alias_or_no_mimetype_through_the_backend_registry() {
+    let model = include_bytes!("sinemodel.tflite");
+    let registry = BackendRegistry::default();
+
+    for mimetype in &["application/x-tflite", "Application/TFLite-Model", ""] {
+        let backend = registry
+            .load(mimetype, model, AccelerationBackend::NONE)
+            .unwrap();
+        assert_eq!(backend.inputs()[0].name.to_str().unwrap(), "dense_2_input");
+    }
+
+    let error = registry
+        .load("", b"this is not a valid model", AccelerationBackend::NONE)
+        .unwrap_err();
+    assert_eq!(
+        error,
+        Error::Load(LoadError::UnsupportedFormat {
+            mimetype: String::new(),
+            supported: vec![mimetype().to_string()],
+        })
+    );
+}
+
+#[test]
+fn report_the_library_versions() {
+    let version = version().unwrap();
+
+    // runecoral.h and Cargo.toml should be bumped together
+    assert_eq!(version.librunecoral, env!("CARGO_PKG_VERSION"));
+    assert_eq!(version.abi_version, ABI_VERSION);
+    assert!(!version.tflite.is_empty());
+    assert!(version
+        .delegates
+        .iter()
+        .any(|(backend, _)| *backend == AccelerationBackend::XNNPACK));
+}
+
 #[test]
 fn query_available_hardware_backends() {
     let backends = AccelerationBackend::currently_available();
@@ -80,3 +238,346 @@ fn query_available_hardware_backends() {
         backends.contains(AccelerationBackend::GPU),
     );
 }
+
+#[test]
+#[cfg(not(feature = "edgetpu_acceleration"))]
+fn there_are_no_edgetpus_without_the_edgetpu_feature() {
+    assert!(edgetpu_devices().is_empty());
+}
+
+#[test]
+fn selecting_a_missing_edgetpu_fails() {
+    let model = include_bytes!("sinemodel.tflite");
+    let selector = EdgeTpuSelector::Path("/dev/does-not-exist".to_string());
+
+    let result = InferenceContext::builder()
+        .acceleration_backend(AccelerationBackend::EDGETPU)
+        .edgetpu_device(selector.clone())
+        .build(mimetype(), model);
+
+    assert_eq!(
+        result.unwrap_err(),
+        Error::Load(LoadError::EdgeTpuNotFound {
+            selector,
+            available: edgetpu_devices(),
+        })
+    );
+}
+
+#[test]
+fn the_edgetpu_device_is_ignored_by_other_backends() {
+    let model = include_bytes!("sinemodel.tflite");
+
+    let ctx = InferenceContext::builder()
+        .acceleration_backend(AccelerationBackend::NONE)
+        .edgetpu_device(42)
+        .build(mimetype(), model)
+        .unwrap();
+
+    assert_eq!(ctx.opcount(), 3);
+}
+
+#[test]
+fn invalid_edgetpu_options_are_rejected() {
+    let model = include_bytes!("sinemodel.tflite");
+    let options = EdgeTpuOptions {
+        usb_max_bulk_in_queue_length: Some(1000),
+        ..Default::default()
+    };
+
+    let result = InferenceContext::builder()
+        .edgetpu_options(options)
+        .build(mimetype(), model);
+
+    assert!(matches!(
+        result.unwrap_err(),
+        Error::Load(LoadError::InvalidEdgeTpuOption { .. })
+    ));
+}
+
+#[test]
+fn xnnpack_matches_the_reference_kernels() {
+    let model = include_bytes!("sinemodel.tflite");
+    assert!(AccelerationBackend::currently_available().contains(AccelerationBackend::XNNPACK));
+
+    let input = [0.5_f32];
+    let report = Comparison::new(
+        Target::new(model, AccelerationBackend::NONE),
+        Target::new(model, AccelerationBackend::XNNPACK),
+    )
+    .run(&[Tensor::from_slice(&input, &[1, 1])])
+    .unwrap();
+
+    assert_eq!(report.outputs.len(), 1);
+    let output = &report.outputs[0];
+    assert_eq!(output.name, "Identity");
+    assert!(output.max_abs_error < 1e-5, "{}", report);
+    assert!(output.top_k.top1_matches);
+}
+
+#[test]
+fn comparisons_reject_incorrectly_sized_inputs() {
+    let model = include_bytes!("sinemodel.tflite");
+    let comparison = Comparison::new(
+        Target::new(model, AccelerationBackend::NONE),
+        Target::new(model, AccelerationBackend::NONE),
+    );
+
+    let input = [0_u8; 2];
+    let err = comparison
+        .run(&[Tensor::from_slice(&input, &[2])])
+        .unwrap_err();
+
+    assert_eq!(
+        err.to_string(),
+        "The inputs don't match the reference model: Expected tensor 0 to be 4 bytes of f32, but it is 2 bytes of u8"
+    );
+}
+
+#[test]
+fn benchmark_the_sine_model() {
+    let model = include_bytes!("sinemodel.tflite");
+
+    let report = Benchmark::new(model).warmup(2).iterations(20).run().unwrap();
+
+    let latency = &report.latency;
+    assert_eq!(latency.iterations, 20);
+    assert!(latency.min <= latency.p50 && latency.p50 <= latency.p99);
+    assert!(latency.p99 <= latency.max);
+    let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+    assert_eq!(json["iterations"], 20);
+}
+
+#[test]
+fn measure_the_sine_model_memory_usage() {
+    let model = include_bytes!("sinemodel.tflite");
+    let ctx =
+        InferenceContext::create_context(mimetype(), model, AccelerationBackend::NONE).unwrap();
+
+    let stats = ctx.memory_stats();
+
+    assert_eq!(stats.model, model.len());
+    // The input, output, and intermediate activations are all f32[1, 16] or
+    // smaller, so even with padding the arena should be tiny
+    assert!(stats.arena > 0);
+    assert!(stats.arena < 1024, "{:?}", stats);
+    assert_eq!(stats.dynamic, 0);
+    assert_eq!(stats.delegate, 0);
+    assert!(stats.total() >= stats.model + stats.arena);
+}
+
+#[test]
+fn profile_the_sine_model() {
+    let model = include_bytes!("sinemodel.tflite");
+    let mut ctx =
+        InferenceContext::create_context(mimetype(), model, AccelerationBackend::NONE).unwrap();
+    ctx.enable_profiling();
+
+    let input = [0.5_f32];
+    let mut output = [0_f32];
+    ctx.infer(
+        &[Tensor::from_slice(&input, &[1])],
+        &mut [TensorMut::from_slice(&mut output, &[1])],
+    )
+    .unwrap();
+
+    let profile = ctx.profile();
+    let op_names: Vec<_> = profile.events.iter().map(|e| e.op_name.as_str()).collect();
+    assert_eq!(op_names, ["FULLY_CONNECTED"; 3]);
+    assert!(profile
+        .events
+        .iter()
+        .all(|e| e.placement == Placement::Cpu));
+
+    let trace: serde_json::Value = serde_json::from_str(&profile.to_chrome_trace()).unwrap();
+    assert_eq!(trace["traceEvents"].as_array().unwrap().len(), 3);
+
+    ctx.disable_profiling();
+    ctx.infer(
+        &[Tensor::from_slice(&input, &[1])],
+        &mut [TensorMut::from_slice(&mut output, &[1])],
+    )
+    .unwrap();
+    assert!(ctx.profile().events.is_empty());
+}
+
+#[test]
+fn load_an_invalid_model() {
+    let result = Model::load(b"this is not a valid model");
+
+    assert_eq!(result.unwrap_err(), LoadError::InternalError);
+}
+
+#[test]
+fn inspect_the_sine_model_graph() {
+    let model = Model::load(include_bytes!("sinemodel.tflite")).unwrap();
+
+    let subgraphs: Vec<_> = model.subgraphs().collect();
+    assert_eq!(subgraphs.len(), 1);
+    let graph = &subgraphs[0];
+
+    let input = graph.tensor(graph.inputs[0]).unwrap();
+    assert_eq!(input.name.to_str().unwrap(), "dense_2_input");
+    assert_eq!(input.element_type, ElementType::Float32);
+    assert_eq!(input.shape.as_ref(), &[1, 1]);
+    let output = graph.tensor(graph.outputs[0]).unwrap();
+    assert_eq!(output.name.to_str().unwrap(), "Identity");
+
+    assert_eq!(graph.operators.len(), 3);
+    for op in &graph.operators {
+        assert_eq!(op.name.to_str().unwrap(), "FULLY_CONNECTED");
+        assert_eq!(op.custom_name, None);
+
+        // Each dense layer has constant weights
+        let weights = graph.tensor(op.inputs[1]).unwrap();
+        assert!(weights.buffer_size > 0);
+    }
+}
+
+#[test]
+fn verify_the_sine_model() {
+    let model = include_bytes!("sinemodel.tflite");
+
+    let info = Model::verify(model).unwrap();
+
+    assert_eq!(info.size, model.len());
+    assert_eq!(info.schema_version, 3);
+    assert_eq!(info.subgraphs, 1);
+}
+
+#[test]
+fn verify_rejects_invalid_models() {
+    let model = include_bytes!("sinemodel.tflite");
+
+    assert_eq!(Model::verify(&model[..4]), Err(VerifyError::Truncated));
+    assert_eq!(
+        Model::verify(b"this is not a valid model"),
+        Err(VerifyError::BadIdentifier)
+    );
+    let mut corrupted = model.to_vec();
+    corrupted.truncate(model.len() / 2);
+    assert!(Model::verify(&corrupted).is_err());
+}
+
+#[test]
+fn the_sine_model_has_no_metadata() {
+    let model = Model::load(include_bytes!("sinemodel.tflite")).unwrap();
+
+    assert_eq!(model.metadata_buffer(TFLITE_METADATA), None);
+    assert_eq!(model.metadata().unwrap(), None);
+    assert!(matches!(
+        model.associated_file("labels.txt"),
+        Err(MetadataError::Zip(_))
+    ));
+}
+
+#[test]
+fn the_sine_model_only_uses_supported_operators() {
+    let model = Model::load(include_bytes!("sinemodel.tflite")).unwrap();
+
+    model.check_compatibility().unwrap();
+}
+
+#[test]
+fn the_sine_model_was_not_compiled_for_the_edgetpu() {
+    let model = Model::load(include_bytes!("sinemodel.tflite")).unwrap();
+
+    assert!(!model.requires_edgetpu());
+}
+
+#[test]
+fn run_inference_with_a_custom_op_resolver() {
+    let mut resolver = OpResolver::new().unwrap();
+    resolver
+        .add_custom("Unused", 1, |_: &[Tensor<'_>], _: &mut [TensorMut<'_>]| Ok(()))
+        .unwrap();
+    let model = include_bytes!("sinemodel.tflite");
+    Model::load(model)
+        .unwrap()
+        .check_compatibility_with(&resolver)
+        .unwrap();
+
+    let mut ctx = InferenceContext::builder()
+        .op_resolver(&resolver)
+        .build(mimetype(), model)
+        .unwrap();
+    // The context keeps its own reference to the resolver
+    drop(resolver);
+
+    let input = [0.5_f32];
+    let mut output = [0_f32];
+    ctx.infer(
+        &[Tensor::from_slice(&input, &[1])],
+        &mut [TensorMut::from_slice(&mut output, &[1])],
+    )
+    .unwrap();
+
+    assert_eq!(round(output[0]), round(0.4540305));
+}
+
+#[test]
+fn save_and_restore_the_sine_model_state() {
+    let model = include_bytes!("sinemodel.tflite");
+    let mut ctx =
+        InferenceContext::create_context(mimetype(), model, AccelerationBackend::NONE).unwrap();
+
+    // The sine model is stateless, so there's nothing to save
+    let state = ctx.save_state();
+    assert!(state.tensors().is_empty());
+
+    ctx.reset_variable_tensors().unwrap();
+    ctx.restore_state(&state).unwrap();
+    let err = ctx
+        .restore_state(&ContextState::new(vec![vec![0; 4]]))
+        .unwrap_err();
+    assert_eq!(
+        err,
+        RestoreError::TensorCount {
+            expected: 0,
+            actual: 1
+        }
+    );
+}
+
+#[test]
+fn inspect_the_sine_model_intermediate_tensors() {
+    let model = include_bytes!("sinemodel.tflite");
+    let mut ctx = InferenceContext::builder()
+        .preserve_all_tensors(true)
+        .build(mimetype(), model)
+        .unwrap();
+
+    let input = [0.5_f32];
+    let mut output = [0_f32];
+    ctx.infer(
+        &[Tensor::from_slice(&input, &[1])],
+        &mut [TensorMut::from_slice(&mut output, &[1])],
+    )
+    .unwrap();
+
+    let tensors = ctx.tensors();
+    // The input, output, 2 intermediate activations, and each layer's weights
+    // and biases
+    assert!(tensors.len() >= 10);
+    let identity = tensors
+        .iter()
+        .find(|t| t.descriptor.name.to_str().unwrap() == "Identity")
+        .unwrap();
+    let data = identity.data.as_ref().unwrap();
+    assert_eq!(data.as_slice::<f32>().unwrap(), &output);
+    // Every activation should have been preserved
+    for tensor in &tensors {
+        assert!(tensor.data.is_some(), "{:?}", tensor.descriptor);
+    }
+}
+
+#[cfg(feature = "testing")]
+#[test]
+fn the_sine_model_matches_its_golden_outputs() {
+    use hotg_runecoral::testing::GoldenTest;
+
+    let model = include_bytes!("sinemodel.tflite");
+    let golden = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/sinemodel");
+
+    GoldenTest::new(model).run(golden).unwrap();
+}